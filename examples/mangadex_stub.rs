@@ -0,0 +1,196 @@
+//! Serves the MangaDex fixtures so the connector can be used offline.
+//!
+//! ```sh
+//! cargo run --example mangadex_stub
+//! MANGADEX_API_URL=http://127.0.0.1:8000 cargo run
+//! ```
+//!
+//! `GET /manga/{id}/feed?...` answers with `fixtures/mangadex/manga/{id}/feed.json`,
+//! query strings are ignored. Page images under `/data/` and covers under
+//! `/covers/` are a blank png.
+//!
+//! Refresh the fixtures with responses of the real API with
+//!
+//! ```sh
+//! cargo run --example mangadex_stub -- record {manga id}...
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+const ADDRESS: &str = "127.0.0.1:8000";
+const API_URL: &str = "https://api.mangadex.org";
+/// Chapters kept from a recorded feed.
+const RECORDED_CHAPTERS: usize = 8;
+
+const BLANK_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xff, 0xff, 0x3f,
+    0x00, 0x05, 0xfe, 0x02, 0xfe, 0xa7, 0x35, 0x81, 0x84, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e,
+    0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/mangadex")
+}
+
+fn fixture_path(root: &Path, path: &str) -> Option<PathBuf> {
+    let path = path.split('?').next()?.trim_matches('/');
+    if path.is_empty() || path.split('/').any(|part| part == "..") {
+        return None;
+    }
+    Some(root.join(format!("{}.json", path)))
+}
+
+async fn respond(mut stream: TcpStream, root: PathBuf) -> std::io::Result<()> {
+    let mut buffer = vec![0; 8192];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+    let (status, content_type, body) = if path.starts_with("/data/") || path.starts_with("/covers/")
+    {
+        ("200 OK", "image/png", BLANK_PNG.to_vec())
+    } else {
+        match fixture_path(&root, &path) {
+            Some(file) => match fs::read(&file).await {
+                Ok(body) => ("200 OK", "application/json", body),
+                Err(_) => (
+                    "404 Not Found",
+                    "application/json",
+                    b"{\"result\":\"error\"}".to_vec(),
+                ),
+            },
+            None => (
+                "404 Not Found",
+                "application/json",
+                b"{\"result\":\"error\"}".to_vec(),
+            ),
+        }
+    };
+    println!("{} {}", status, path);
+
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await
+}
+
+/// Answers every connection on `listener` from the fixtures in `root`.
+pub async fn serve(listener: TcpListener, root: PathBuf) -> std::io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let root = root.clone();
+        tokio::spawn(async move {
+            if let Err(err) = respond(stream, root).await {
+                eprintln!("{}", err);
+            }
+        });
+    }
+}
+
+async fn fetch(client: &reqwest::Client, path: &str, query: &[(&str, &str)]) -> Value {
+    client
+        .get(format!("{}/{}", API_URL, path))
+        .query(query)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .unwrap_or_else(|err| panic!("failed to fetch {}: {}", path, err))
+        .text()
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|body| serde_json::from_str(&body).map_err(|err| err.to_string()))
+        .unwrap_or_else(|err| panic!("failed to decode {}: {}", path, err))
+}
+
+async fn write_fixture(root: &Path, path: &str, value: &Value) {
+    let file = fixture_path(root, path).unwrap();
+    fs::create_dir_all(file.parent().unwrap()).await.unwrap();
+    let json = serde_json::to_string_pretty(value).unwrap();
+    fs::write(&file, json + "\n").await.unwrap();
+    println!("recorded {:?}", file);
+}
+
+/// Replaces the fixtures with live responses for `ids`: the listing and
+/// details of each manga, and the feed and pages of the first.
+async fn record(root: &Path, ids: &[String]) {
+    let client = reqwest::Client::builder()
+        .user_agent("mangetsu fixture recorder")
+        .build()
+        .unwrap();
+
+    let mut listing = vec![("includes[]", "cover_art")];
+    listing.extend(ids.iter().map(|id| ("ids[]", id.as_str())));
+    write_fixture(root, "manga", &fetch(&client, "manga", &listing).await).await;
+
+    for id in ids {
+        let path = format!("manga/{}", id);
+        let details = [
+            ("includes[]", "cover_art"),
+            ("includes[]", "author"),
+            ("includes[]", "artist"),
+        ];
+        write_fixture(root, &path, &fetch(&client, &path, &details).await).await;
+    }
+
+    let path = format!("manga/{}/feed", ids[0]);
+    let limit = RECORDED_CHAPTERS.to_string();
+    let feed_query = [
+        ("limit", limit.as_str()),
+        ("translatedLanguage[]", "en"),
+        ("order[volume]", "desc"),
+        ("order[chapter]", "desc"),
+        ("includes[]", "scanlation_group"),
+    ];
+    let mut feed = fetch(&client, &path, &feed_query).await;
+    // The stub ignores offsets, a feed claiming more chapters would be paged forever.
+    let recorded = feed["data"].as_array().map_or(0, Vec::len);
+    feed["total"] = recorded.into();
+    write_fixture(root, &path, &feed).await;
+
+    let chapter = feed["data"]
+        .as_array()
+        .and_then(|chapters| {
+            chapters
+                .iter()
+                .find(|chapter| chapter["attributes"]["externalUrl"].is_null())
+        })
+        .and_then(|chapter| chapter["id"].as_str())
+        .expect("no chapter hosted on MangaDex in the feed");
+    let path = format!("at-home/server/{}", chapter);
+    let mut at_home = fetch(&client, &path, &[]).await;
+    // Pages are then served by the stub too.
+    at_home["baseUrl"] = format!("http://{}", ADDRESS).into();
+    write_fixture(root, &path, &at_home).await;
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let root = fixtures_dir();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(("record", ids)) = args.split_first().map(|(mode, ids)| (mode.as_str(), ids)) {
+        if ids.is_empty() {
+            eprintln!("usage: mangadex_stub record {{manga id}}...");
+            std::process::exit(1);
+        }
+        record(&root, ids).await;
+        return Ok(());
+    }
+
+    let listener = TcpListener::bind(ADDRESS).await?;
+    println!("Serving {:?} on http://{}", root, ADDRESS);
+    serve(listener, root).await
+}
@@ -0,0 +1,357 @@
+//! Headless access to the connectors and the download pipeline, for scripts.
+//! Runs instead of the app when given a subcommand.
+
+use std::{str::FromStr, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use druid::im::Vector;
+use futures::{stream, StreamExt, TryStreamExt};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use reqwest::Url;
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::{
+    core::{error::Error, export::OutputFormat, Chapter, GlobalAPI, Manga},
+    data::{start_download, Config, DownloadConfig},
+};
+
+#[derive(StructOpt)]
+#[structopt(name = "mangetsu", about = "Manga reader and downloader.")]
+pub struct Cli {
+    /// Prints JSON instead of text.
+    #[structopt(long, global = true)]
+    json: bool,
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Lists the available connectors.
+    Connectors,
+    /// Searches every connector for a title.
+    Search { query: String },
+    /// Shows the details of a manga.
+    Info { url: String },
+    /// Lists the chapters of a manga, oldest first.
+    Chapters { url: String },
+    /// Downloads chapters of a manga to the download folder.
+    Download {
+        url: String,
+        /// Chapter numbers to download, e.g. `10-20` or `12`. Every chapter
+        /// when unset.
+        #[structopt(long)]
+        range: Option<ChapterRange>,
+        /// How chapters are stored, the configured format when unset.
+        #[structopt(
+            long,
+            possible_values = &["folder", "cbz", "both"],
+            parse(try_from_str = parse_format)
+        )]
+        format: Option<OutputFormat>,
+    },
+}
+
+/// Chapter numbers from `start` to `end`, both included.
+#[derive(Clone, Copy, Debug)]
+pub struct ChapterRange {
+    start: f32,
+    end: f32,
+}
+
+impl ChapterRange {
+    fn contains(&self, chapter: &Chapter) -> bool {
+        chapter
+            .number
+            .map(|number| number >= self.start && number <= self.end)
+            .unwrap_or(false)
+    }
+}
+
+impl FromStr for ChapterRange {
+    type Err = String;
+
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let parse = |number: &str| {
+            number
+                .trim()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid chapter number `{}`", number))
+        };
+        let range = Self {
+            start: parse(start)?,
+            end: parse(end)?,
+        };
+        if range.start > range.end {
+            return Err(format!("`{}` ends before it starts", range_str(&range)));
+        }
+        Ok(range)
+    }
+}
+
+fn range_str(range: &ChapterRange) -> String {
+    format!("{}-{}", range.start, range.end)
+}
+
+fn parse_format(format: &str) -> Result<OutputFormat, String> {
+    match format {
+        "folder" => Ok(OutputFormat::Folder),
+        "cbz" => Ok(OutputFormat::Cbz),
+        "both" => Ok(OutputFormat::Both),
+        _ => Err(format!("unknown format `{}`", format)),
+    }
+}
+
+#[derive(Serialize)]
+struct ConnectorRow {
+    id: String,
+    label: String,
+    url: String,
+    tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ChapterRow {
+    number: Option<f32>,
+    volume: Option<Arc<str>>,
+    title: Arc<str>,
+    url: Arc<str>,
+    uploaded: Option<DateTime<Utc>>,
+    scanlator: Option<Arc<str>>,
+}
+
+impl From<&Chapter> for ChapterRow {
+    fn from(chapter: &Chapter) -> Self {
+        Self {
+            number: chapter.number,
+            volume: chapter.volume.clone(),
+            title: chapter.title.clone(),
+            url: chapter.url.clone(),
+            uploaded: chapter.uploaded,
+            scanlator: chapter.scanlator.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DownloadRow {
+    #[serde(flatten)]
+    chapter: ChapterRow,
+    error: Option<String>,
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), Error> {
+    let json =
+        serde_json::to_string_pretty(value).map_err(|err| Error::IoError(err.to_string()))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn chapter_number(chapter: &Chapter) -> String {
+    chapter
+        .number
+        .map(|number| number.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+async fn fetch_manga(url: &str) -> Result<Manga, Error> {
+    let connector = GlobalAPI::global().connector_for_url(url)?;
+    let url = Url::parse(url).map_err(|err| Error::RequestFail(err.to_string()))?;
+    connector.get_manga_from_url(url).await
+}
+
+/// Chapters of the manga at `url`, oldest first as in the app.
+async fn fetch_chapters(url: &str) -> Result<(Manga, Vec<Chapter>), Error> {
+    let manga = fetch_manga(url).await?;
+    let mut chapters: Vec<Chapter> = manga.get_chapters().try_collect().await?;
+    chapters.reverse();
+    Ok((manga, chapters))
+}
+
+pub async fn run(cli: Cli, config: Config) -> Result<(), Error> {
+    let json = cli.json;
+    match cli.command {
+        None => Ok(()),
+        Some(Command::Connectors) => {
+            let rows: Vec<ConnectorRow> = GlobalAPI::global()
+                .connectors
+                .values()
+                .map(|connector| {
+                    let info = connector.get_connector_info();
+                    ConnectorRow {
+                        id: info.id,
+                        label: info.label,
+                        url: info.url.to_string(),
+                        tags: info.tags,
+                    }
+                })
+                .collect();
+            if json {
+                return print_json(&rows);
+            }
+            for row in rows {
+                println!("{}\t{}\t{}", row.id, row.label, row.url);
+            }
+            Ok(())
+        }
+        Some(Command::Search { query }) => {
+            let mut results = GlobalAPI::global().search(&query, 1);
+            let mut mangas = Vec::new();
+            // A connector failing leaves the others' results.
+            while let Some(result) = results.next().await {
+                match result {
+                    Ok(manga) if json => mangas.push(manga),
+                    Ok(manga) => println!("{}\t{}\t{}", manga.connector, manga.title, manga.url),
+                    Err(err) => eprintln!("warning: {}", err),
+                }
+            }
+            if json {
+                print_json(&mangas)?;
+            }
+            Ok(())
+        }
+        Some(Command::Info { url }) => {
+            let manga = fetch_manga(&url).await?;
+            if json {
+                return print_json(&manga);
+            }
+            println!("Title: {}", manga.title);
+            println!("Url: {}", manga.url);
+            println!("Connector: {}", manga.connector);
+            if let Some(details) = &manga.details {
+                let join = |values: &Vector<Arc<str>>| {
+                    values
+                        .iter()
+                        .map(|value| value.as_ref())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                println!("Status: {}", details.status);
+                if !details.alt_titles.is_empty() {
+                    println!("Alternative titles: {}", join(&details.alt_titles));
+                }
+                if !details.authors.is_empty() {
+                    println!("Authors: {}", join(&details.authors));
+                }
+                if !details.genres.is_empty() {
+                    println!("Genres: {}", join(&details.genres));
+                }
+                if let Some(last_updated) = &details.last_updated {
+                    println!("Last updated: {}", last_updated);
+                }
+                if let Some(description) = &details.description {
+                    println!("\n{}", description.trim());
+                }
+            }
+            Ok(())
+        }
+        Some(Command::Chapters { url }) => {
+            let (_, chapters) = fetch_chapters(&url).await?;
+            if json {
+                let rows: Vec<ChapterRow> = chapters.iter().map(ChapterRow::from).collect();
+                return print_json(&rows);
+            }
+            for chapter in &chapters {
+                println!("{:>8}  {}", chapter_number(chapter), chapter.title);
+            }
+            Ok(())
+        }
+        Some(Command::Download { url, range, format }) => {
+            let (manga, chapters) = fetch_chapters(&url).await?;
+            let chapters: Vec<Chapter> = chapters
+                .into_iter()
+                .filter(|chapter| match range {
+                    Some(range) => range.contains(chapter),
+                    None => true,
+                })
+                .collect();
+            if chapters.is_empty() {
+                return Err(Error::RequestFail(match range {
+                    Some(range) => {
+                        format!("{} has no chapter in {}", manga.title, range_str(&range))
+                    }
+                    None => format!("{} has no chapters", manga.title),
+                }));
+            }
+            config.downloads.validate()?;
+            let format = format.unwrap_or(config.output_format);
+            let results = download(&manga, chapters, format, &config.downloads).await;
+
+            let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+            if json {
+                let rows: Vec<DownloadRow> = results
+                    .iter()
+                    .map(|(chapter, result)| DownloadRow {
+                        chapter: ChapterRow::from(chapter),
+                        error: result.as_ref().err().map(ToString::to_string),
+                    })
+                    .collect();
+                print_json(&rows)?;
+            } else {
+                for (chapter, result) in &results {
+                    match result {
+                        Ok(()) => println!("Downloaded {}", chapter.title),
+                        Err(err) => println!("Failed {}: {}", chapter.title, err),
+                    }
+                }
+                if let Some(root) = config.downloads.root() {
+                    println!("Saved to {}", root.display());
+                }
+            }
+            if failed > 0 {
+                return Err(Error::RequestFail(format!(
+                    "{} of {} chapters failed",
+                    failed,
+                    results.len()
+                )));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Downloads `chapters` as many at a time as the app would, with a progress
+/// bar for each running chapter and one for them all.
+async fn download(
+    manga: &Manga,
+    chapters: Vec<Chapter>,
+    format: OutputFormat,
+    config: &DownloadConfig,
+) -> Vec<(Chapter, Result<(), Error>)> {
+    let bars = MultiProgress::new();
+    let total = bars.add(ProgressBar::new(chapters.len() as u64));
+    total.set_style(
+        ProgressStyle::with_template("{prefix:30!} [{bar:30}] {pos}/{len} chapters")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+    total.set_prefix(manga.title.to_string());
+    let chapter_style = ProgressStyle::with_template("{prefix:30!} [{bar:30}] {percent:>3}% {msg}")
+        .unwrap()
+        .progress_chars("=> ");
+
+    let results = stream::iter(chapters)
+        .map(|chapter| {
+            let bar = bars.insert_before(&total, ProgressBar::new(1000));
+            bar.set_style(chapter_style.clone());
+            bar.set_prefix(chapter.title.to_string());
+            let total = &total;
+            async move {
+                let result = start_download(&chapter, format, config, |progress| {
+                    bar.set_position((progress.fraction * 1000.) as u64);
+                    bar.set_message(progress.to_string());
+                })
+                .await;
+                bar.finish_and_clear();
+                total.inc(1);
+                (chapter, result)
+            }
+        })
+        .buffered(config.chapters_for(&manga.connector))
+        .collect()
+        .await;
+    total.finish_and_clear();
+    results
+}
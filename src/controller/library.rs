@@ -0,0 +1,117 @@
+use druid::{
+    widget::{prelude::*, Controller},
+    Command,
+};
+
+use chrono::Utc;
+
+use crate::data::{check_updates, cmd, AppState};
+
+/// Keeps the library in sync with the manga page and saves it on every change.
+/// Also runs the update checks of watched manga.
+pub struct LibraryController;
+
+impl LibraryController {
+    fn update_command(&self, ctx: &mut EventCtx, cmd: &Command, data: &mut AppState) -> bool {
+        if cmd.is(cmd::CHECK_UPDATES) {
+            if !data.checking_updates {
+                let mangas: Vec<_> = data
+                    .library
+                    .watched()
+                    .map(|entry| entry.manga.clone())
+                    .collect();
+                log::info!("checking {} manga for updates", mangas.len());
+                data.checking_updates = true;
+                tokio::spawn(check_updates(mangas, ctx.get_external_handle()));
+            }
+        } else if cmd.is(cmd::UPDATES_CHECKED) {
+            data.checking_updates = false;
+        } else if let Some((manga_url, chapters)) = cmd.get(cmd::UPDATES_FOUND) {
+            let new_chapters = data
+                .library
+                .apply_update(manga_url, chapters.clone(), Utc::now());
+            if data.config.updates.auto_download {
+                // Oldest first, the queue downloads in order.
+                for chapter in new_chapters {
+                    ctx.submit_command(cmd::DOWNLOAD_CHAPTER.with(chapter));
+                }
+            }
+            data.library.save();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn command(&self, cmd: &Command, data: &mut AppState) -> bool {
+        if let Some(manga) = cmd.get(cmd::ADD_TO_LIBRARY) {
+            let chapters = data
+                .manga_detail
+                .as_ref()
+                .filter(|detail| detail.manga.url == manga.url)
+                .map(|detail| detail.chapters.clone())
+                .unwrap_or_default();
+            data.library.add(manga.clone(), chapters);
+        } else if let Some(manga_url) = cmd.get(cmd::REMOVE_FROM_LIBRARY) {
+            data.library.remove(manga_url);
+        } else if let Some((chapter, read)) = cmd.get(cmd::SET_CHAPTER_READ) {
+            data.library
+                .set_read(&chapter.manga.url, chapter.url.clone(), *read);
+        } else if let Some((manga_url, watch)) = cmd.get(cmd::SET_WATCH) {
+            data.library.set_watch(manga_url, *watch);
+        } else {
+            return false;
+        }
+
+        if let Some(detail) = data.manga_detail.as_mut() {
+            let entry = data.library.get(&detail.manga.url);
+            detail.in_library = entry.is_some();
+            detail.watch = entry.map(|entry| entry.watch).unwrap_or_default();
+            detail.read = entry.map(|entry| entry.read.clone()).unwrap_or_default();
+        }
+        data.library.save();
+        true
+    }
+}
+
+impl<W> Controller<AppState, W> for LibraryController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Command(cmd) = event {
+            if self.command(cmd, data) || self.update_command(ctx, cmd, data) {
+                ctx.set_handled();
+                return;
+            }
+        }
+
+        let chapters = data
+            .manga_detail
+            .as_ref()
+            .map(|detail| detail.chapters.clone());
+        child.event(ctx, event, data, env);
+
+        // Store freshly fetched chapter lists of manga in the library.
+        if let Some(detail) = &data.manga_detail {
+            let changed = chapters
+                .map(|chapters| !chapters.same(&detail.chapters))
+                .unwrap_or(true);
+            if changed
+                && !detail.chapters.is_empty()
+                && data
+                    .library
+                    .update_chapters(&detail.manga.url, &detail.chapters)
+            {
+                data.library.save();
+            }
+        }
+    }
+}
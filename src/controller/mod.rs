@@ -1,3 +1,7 @@
+mod library;
 mod nav;
+mod reader;
 
+pub use library::LibraryController;
 pub use nav::NavController;
+pub use reader::ReaderController;
@@ -3,27 +3,75 @@ use druid::{
     widget::{prelude::*, Controller},
 };
 
-use crate::data::{cmd, AppState, MangaDetail, Nav};
+use crate::{
+    core::{error::Error, Chapter},
+    data::{cmd, AppState, MangaDetail, Nav, ReaderState},
+};
 
 pub struct NavController;
 
 impl NavController {
+    /// Looks up a chapter along with the chapter list of its manga, from the
+    /// reader, the manga page or the library.
+    fn find_chapter(&self, url: &str, data: &AppState) -> Option<(Chapter, Vector<Chapter>)> {
+        let lists = data
+            .reader
+            .iter()
+            .map(|reader| &reader.chapters)
+            .chain(data.manga_detail.iter().map(|detail| &detail.chapters))
+            .chain(data.library.entries.iter().map(|entry| &entry.chapters));
+        for chapters in lists {
+            if let Some(chapter) = chapters.iter().find(|chapter| chapter.url.as_ref() == url) {
+                return Some((chapter.clone(), chapters.clone()));
+            }
+        }
+        data.library
+            .updates
+            .iter()
+            .find(|update| update.chapter.url.as_ref() == url)
+            .map(|update| (update.chapter.clone(), Vector::unit(update.chapter.clone())))
+    }
+
     fn load_route_data(&self, ctx: &mut EventCtx, data: &mut AppState) {
         match &data.route {
             Nav::Home => {}
-            Nav::Downloads => {}
+            Nav::Downloads => {
+                ctx.submit_command(cmd::SCAN_DOWNLOADS);
+            }
+            Nav::Library => {}
+            Nav::Updates => {}
+            Nav::Search(_) => {
+                data.search_results = Vector::new();
+            }
             Nav::MangaPage(url) => {
-                if let Some(manga) = data.manga_cache.lock().unwrap().get_mut(url) {
-                    data.manga_detail = Some(MangaDetail {
-                        manga: manga.to_owned(),
-                        start: 1,
-                        end: 1,
-                        chapters: Vector::new(),
+                let cached = data
+                    .manga_cache
+                    .lock()
+                    .unwrap()
+                    .get_mut(url)
+                    .filter(|manga| manga.details.is_some())
+                    .cloned()
+                    .or_else(|| {
+                        data.library
+                            .get(url)
+                            .map(|entry| entry.manga.clone())
+                            .filter(|manga| manga.details.is_some())
                     });
+                if let Some(manga) = cached {
+                    data.manga_detail = Some(MangaDetail::new(manga, &data.library));
                 } else {
+                    data.manga_detail = None;
                     ctx.submit_command(cmd::FETCH_MANGA_DETAIL.with(url.to_owned()));
                 }
             }
+            Nav::Reader(url) => {
+                data.reader = self
+                    .find_chapter(url, data)
+                    .map(|(chapter, chapters)| ReaderState::new(chapter, chapters));
+                if data.reader.is_none() {
+                    data.error = Some(Error::NotFound);
+                }
+            }
         };
         data.mangas = Vector::new();
     }
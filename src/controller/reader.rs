@@ -0,0 +1,114 @@
+use druid::{
+    widget::{prelude::*, Controller},
+    KbKey, Selector,
+};
+
+use crate::data::{cmd, AppState, Nav, ReadingMode};
+
+const TAKE_FOCUS: Selector = Selector::new("app.reader.take-focus");
+
+/// Page and chapter turns of the reader, from commands and the keyboard.
+pub struct ReaderController;
+
+impl ReaderController {
+    fn turn_page(&self, ctx: &mut EventCtx, data: &mut AppState, delta: isize) {
+        let paged = data.config.reader.mode == ReadingMode::Paged;
+        let reader = match data.reader.as_mut() {
+            Some(reader) => reader,
+            None => return,
+        };
+        if paged && reader.turn(delta) {
+            if reader.is_last_page() {
+                ctx.submit_command(cmd::SET_CHAPTER_READ.with((reader.chapter.clone(), true)));
+            }
+        } else if !paged || !reader.pages.is_empty() {
+            self.turn_chapter(ctx, data, delta.signum());
+        }
+    }
+
+    fn turn_chapter(&self, ctx: &mut EventCtx, data: &mut AppState, delta: isize) {
+        let reader = match data.reader.as_ref() {
+            Some(reader) => reader,
+            None => return,
+        };
+        let chapter = if delta > 0 {
+            ctx.submit_command(cmd::SET_CHAPTER_READ.with((reader.chapter.clone(), true)));
+            reader.next_chapter()
+        } else {
+            reader.previous_chapter()
+        };
+        if let Some(chapter) = chapter {
+            ctx.submit_command(cmd::NAVIGATE.with(Nav::Reader(chapter.url.clone())));
+        }
+    }
+}
+
+impl<W> Controller<AppState, W> for ReaderController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(TAKE_FOCUS) => {
+                ctx.request_focus();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::TURN_PAGE) => {
+                self.turn_page(ctx, data, *cmd.get_unchecked(cmd::TURN_PAGE));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::TURN_CHAPTER) => {
+                self.turn_chapter(ctx, data, *cmd.get_unchecked(cmd::TURN_CHAPTER));
+                ctx.set_handled();
+            }
+            // The scroll of vertical mode takes the keys, chapters turn from
+            // the toolbar.
+            Event::KeyDown(_) if data.config.reader.mode == ReadingMode::Vertical => {
+                child.event(ctx, event, data, env)
+            }
+            Event::KeyDown(key) => {
+                let forward = if data.config.reader.right_to_left {
+                    -1
+                } else {
+                    1
+                };
+                match &key.key {
+                    KbKey::ArrowRight => self.turn_page(ctx, data, forward),
+                    KbKey::ArrowLeft => self.turn_page(ctx, data, -forward),
+                    KbKey::PageDown => self.turn_page(ctx, data, 1),
+                    KbKey::Character(c) if c == " " => self.turn_page(ctx, data, 1),
+                    KbKey::PageUp => self.turn_page(ctx, data, -1),
+                    _ => return child.event(ctx, event, data, env),
+                }
+                ctx.set_handled();
+            }
+            Event::MouseDown(_) => {
+                ctx.request_focus();
+                child.event(ctx, event, data, env);
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            ctx.register_for_focus();
+            ctx.submit_command(TAKE_FOCUS.to(ctx.widget_id()));
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
@@ -1,27 +1,86 @@
 use std::sync::Arc;
 
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use druid::{Data, Lens};
+use futures::{future, stream};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use crate::core::Connectors;
 
 use super::{connector::StreamResult, GlobalAPI, Manga, Page};
 
+static TITLE_NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bch(?:apter)?\.?\s*(\d+(?:\.\d+)?)").unwrap());
+static URL_NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)chapter[-_](\d+(?:\.\d+)?)").unwrap());
+static TITLE_VOLUME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bvol(?:ume)?\.?\s*(\d+(?:\.\d+)?)").unwrap());
+
 #[derive(Clone, Debug, Lens, Deserialize, Serialize)]
 pub struct Chapter {
     pub url: Arc<str>,
     pub title: Arc<str>,
     pub connector: Connectors,
     pub manga: Manga,
+    #[serde(default)]
+    pub number: Option<f32>,
+    #[serde(default)]
+    pub volume: Option<Arc<str>>,
+    #[serde(default)]
+    pub uploaded: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub scanlator: Option<Arc<str>>,
+    #[serde(default)]
+    pub views: Option<u64>,
 }
 
 impl Chapter {
-    pub fn get_pages(&self) -> StreamResult<Page> {
-        GlobalAPI::global()
-            .connectors
-            .get(&self.connector)
-            .unwrap()
-            .get_pages(self.clone())
+    pub fn get_pages(&self) -> StreamResult<'static, Page> {
+        match GlobalAPI::global().connector(&self.connector) {
+            Ok(connector) => connector.get_pages(self.clone()),
+            Err(err) => Box::pin(stream::once(future::ready(Err(err)))),
+        }
+    }
+
+    /// Extracts the chapter number from a title such as `Vol.2 Chapter 10.5: Title`,
+    /// falling back to urls ending in `chapter-10.5`.
+    pub fn parse_number(title: &str, url: &str) -> Option<f32> {
+        TITLE_NUMBER
+            .captures(title)
+            .or_else(|| URL_NUMBER.captures(url))
+            .and_then(|captures| captures[1].parse().ok())
+    }
+
+    pub fn parse_volume(title: &str) -> Option<Arc<str>> {
+        TITLE_VOLUME
+            .captures(title)
+            .map(|captures| captures[1].into())
+    }
+
+    /// Parses view counters like `1,234`, `12.3K` or `1.2M`.
+    pub fn parse_views(views: &str) -> Option<u64> {
+        let views = views.trim().replace(',', "");
+        let (number, multiplier) = match views.chars().last()?.to_ascii_uppercase() {
+            'K' => (&views[..views.len() - 1], 1e3),
+            'M' => (&views[..views.len() - 1], 1e6),
+            'B' => (&views[..views.len() - 1], 1e9),
+            _ => (&views[..], 1.),
+        };
+        number
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .map(|number| (number * multiplier) as u64)
+    }
+
+    pub fn parse_date(date: &str, formats: &[&str]) -> Option<DateTime<Utc>> {
+        formats.iter().find_map(|format| {
+            NaiveDateTime::parse_from_str(date.trim(), format)
+                .ok()
+                .map(|date| Utc.from_utc_datetime(&date))
+        })
     }
 }
 
@@ -1,35 +1,24 @@
 use std::fmt;
 
 use futures::{future::BoxFuture, stream::BoxStream};
-use regex::Regex;
 use reqwest::Url;
-use scraper::Selector;
 
 use crate::core::{error::Error, Chapter, Manga, Page};
 
 #[derive(Debug, Clone)]
 pub struct ConnectorInfo {
-    pub id: &'static str,
-    pub label: &'static str,
-    pub tags: Vec<&'static str>,
+    pub id: String,
+    pub label: String,
+    pub tags: Vec<String>,
     pub url: Url,
-
-    pub path: &'static str,
-    pub manga_title_filter: Regex,
-    pub chapter_title_filter: Regex,
-    pub query_manga_title: Selector,
-    pub query_mangas_page_count: Selector,
-    pub query_mangas: Selector,
-
-    pub query_icon: Selector,
-
-    pub query_chapters: Selector,
-    pub query_pages: Selector,
 }
 
 pub type StreamResult<'a, T> = BoxStream<'a, Result<T, Error>>;
 pub type FutureResult<'a, T> = BoxFuture<'a, Result<T, Error>>;
 
+/// Covers resolved at once by [`Connector::resolve_cover`] callers.
+pub const COVER_CONCURRENCY: usize = 4;
+
 pub trait Connector {
     fn get_connector_info(&self) -> ConnectorInfo;
 
@@ -43,6 +32,36 @@ pub trait Connector {
 
     fn get_mangas_from_page(&self, page: i32) -> StreamResult<Manga>;
 
+    /// Fills in the cover of a manga whose listing had none, through
+    /// `get_manga_icon`. Mangas that already have one are returned as is, and
+    /// the cover is left empty if it cannot be found.
+    fn resolve_cover(&self, manga: Manga) -> BoxFuture<Manga>
+    where
+        Self: Sync,
+    {
+        Box::pin(async move {
+            if !manga.icon_url.is_empty() {
+                return manga;
+            }
+            let icon_url = match Url::parse(&manga.url) {
+                Ok(url) => self.get_manga_icon(url).await,
+                Err(err) => Err(Error::RequestFail(err.to_string())),
+            };
+            match icon_url {
+                Ok(icon_url) => Manga {
+                    icon_url: icon_url.as_str().into(),
+                    ..manga
+                },
+                Err(err) => {
+                    log::warn!("no cover for {}: {}", manga.url, err);
+                    manga
+                }
+            }
+        })
+    }
+
+    fn search(&self, query: String, page: i32) -> StreamResult<Manga>;
+
     fn get_chapters(&self, manga: Manga) -> StreamResult<Chapter>;
 
     fn get_pages(&self, chapter: Chapter) -> StreamResult<Page>;
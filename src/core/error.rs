@@ -2,10 +2,22 @@ use std::fmt;
 
 use druid::Data;
 
+// `IoError` keeps the name it has had since before the other variants.
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum Error {
     RequestFail(String),
     IoError(String),
+    InvalidConnector(String),
+    InvalidTemplate(String),
+    /// A selector matched nothing, usually because the website layout changed.
+    NoMatch {
+        connector: String,
+        selector: String,
+        url: String,
+    },
+    HttpStatus(u16),
+    NotFound,
 }
 
 impl std::error::Error for Error {}
@@ -15,6 +27,19 @@ impl std::fmt::Display for Error {
         match self {
             Self::RequestFail(err) => f.write_str(err),
             Self::IoError(err) => f.write_str(err),
+            Self::InvalidConnector(err) => write!(f, "Invalid connector definition: {}", err),
+            Self::InvalidTemplate(err) => write!(f, "Invalid download template: {}", err),
+            Self::NoMatch {
+                connector,
+                selector,
+                url,
+            } => write!(
+                f,
+                "{}: selector `{}` matched nothing on page {}",
+                connector, selector, url
+            ),
+            Self::HttpStatus(status) => write!(f, "Request failed with HTTP status {}", status),
+            Self::NotFound => f.write_str("Not found"),
         }
     }
 }
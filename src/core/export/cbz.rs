@@ -0,0 +1,149 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::core::{
+    error::{map_to_string, Error},
+    Chapter, Manga,
+};
+
+use super::{escape_xml, unescape_xml, write_atomic};
+
+pub const COMIC_INFO_FILENAME: &str = "ComicInfo.xml";
+
+/// `ComicInfo.xml` of a chapter, as read by Komga, Kavita and KOReader.
+pub fn comic_info(chapter: &Chapter, page_count: usize) -> String {
+    let mut fields = vec![
+        ("Series", chapter.manga.title.to_string()),
+        ("Title", chapter.title.to_string()),
+    ];
+    if let Some(number) = chapter.number {
+        fields.push(("Number", number.to_string()));
+    }
+    if let Some(volume) = &chapter.volume {
+        fields.push(("Volume", volume.to_string()));
+    }
+    if let Some(details) = &chapter.manga.details {
+        if let Some(description) = &details.description {
+            fields.push(("Summary", description.to_string()));
+        }
+        if !details.authors.is_empty() {
+            let authors: Vec<&str> = details
+                .authors
+                .iter()
+                .map(|author| author.as_ref())
+                .collect();
+            fields.push(("Writer", authors.join(", ")));
+        }
+        if !details.genres.is_empty() {
+            let genres: Vec<&str> = details.genres.iter().map(|genre| genre.as_ref()).collect();
+            fields.push(("Genre", genres.join(", ")));
+        }
+    }
+    if let Some(uploaded) = chapter.uploaded {
+        fields.push(("Year", uploaded.format("%Y").to_string()));
+        fields.push(("Month", uploaded.format("%-m").to_string()));
+        fields.push(("Day", uploaded.format("%-d").to_string()));
+    }
+    if let Some(scanlator) = &chapter.scanlator {
+        fields.push(("ScanInformation", scanlator.to_string()));
+    }
+    fields.push(("Web", chapter.url.to_string()));
+    fields.push(("PageCount", page_count.to_string()));
+    fields.push(("Manga", "Yes".to_string()));
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<ComicInfo xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\">\n",
+    );
+    for (name, value) in fields {
+        xml.push_str(&format!("  <{0}>{1}</{0}>\n", name, escape_xml(&value)));
+    }
+    xml.push_str("</ComicInfo>\n");
+    xml
+}
+
+/// Reads back the chapter of `manga` described by a `ComicInfo.xml` written by
+/// [`comic_info`], for archives that lost their `metadata.json`.
+pub fn read_comic_info(xml: &str, manga: &Manga) -> Result<Chapter, Error> {
+    let field = |name: &str| {
+        let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+        let end = start + xml[start..].find(&format!("</{}>", name))?;
+        Some(unescape_xml(&xml[start..end]))
+    };
+    let url = field("Web").ok_or_else(|| Error::IoError("No url in ComicInfo.xml".to_string()))?;
+    Ok(Chapter {
+        title: field("Title").unwrap_or_else(|| url.clone()).into(),
+        url: url.into(),
+        connector: manga.connector.clone(),
+        manga: manga.clone(),
+        number: field("Number").and_then(|number| number.parse().ok()),
+        volume: field("Volume").map(Into::into),
+        uploaded: None,
+        scanlator: field("ScanInformation").map(Into::into),
+        views: None,
+    })
+}
+
+/// Zips `pages` in order into `output` along with the chapter's `ComicInfo.xml`.
+/// Pages are renamed `000.jpg`, `001.png`... so every reader sorts them right.
+pub fn write_cbz(chapter: &Chapter, pages: &[PathBuf], output: &Path) -> Result<(), Error> {
+    write_atomic(output, |file| {
+        let mut zip = ZipWriter::new(file);
+        // Images are compressed already.
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for (index, page) in pages.iter().enumerate() {
+            let name = match page.extension() {
+                Some(extension) => format!("{:03}.{}", index, extension.to_string_lossy()),
+                None => format!("{:03}", index),
+            };
+            zip.start_file(name, stored)
+                .map_err(map_to_string(Error::IoError))?;
+            let mut page = File::open(page).map_err(map_to_string(Error::IoError))?;
+            io::copy(&mut page, &mut zip).map_err(map_to_string(Error::IoError))?;
+        }
+        zip.start_file(COMIC_INFO_FILENAME, FileOptions::default())
+            .map_err(map_to_string(Error::IoError))?;
+        zip.write_all(comic_info(chapter, pages.len()).as_bytes())
+            .map_err(map_to_string(Error::IoError))?;
+        zip.finish().map_err(map_to_string(Error::IoError))?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Connectors;
+
+    #[test]
+    fn comic_info_reads_back() {
+        let manga = Manga {
+            title: "One & Two".into(),
+            url: "https://example.com/manga".into(),
+            icon_url: "".into(),
+            connector: Connectors::Manganel,
+            details: None,
+        };
+        let chapter = Chapter {
+            url: "https://example.com/manga/chapter-10.5".into(),
+            title: "Vol.2 Chapter 10.5: <Extra>".into(),
+            connector: Connectors::Manganel,
+            manga: manga.clone(),
+            number: Some(10.5),
+            volume: Some("2".into()),
+            uploaded: None,
+            scanlator: Some("Team \"A\"".into()),
+            views: None,
+        };
+        let read = read_comic_info(&comic_info(&chapter, 20), &manga).unwrap();
+        assert_eq!(read.url, chapter.url);
+        assert_eq!(read.title, chapter.title);
+        assert_eq!(read.number, chapter.number);
+        assert_eq!(read.volume, chapter.volume);
+        assert_eq!(read.scanlator, chapter.scanlator);
+    }
+}
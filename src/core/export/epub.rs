@@ -0,0 +1,213 @@
+use std::{
+    io::{Cursor, Write},
+    path::Path,
+};
+
+use chrono::Utc;
+use druid::image;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::core::error::{map_to_string, Error};
+
+use super::{escape_xml, write_atomic, PageFile, VolumeChapter};
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn media_type(extension: &str) -> &'static str {
+    match extension {
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        _ => "image/jpeg",
+    }
+}
+
+struct EpubPage<'a> {
+    file: &'a PageFile,
+    image: String,
+    width: u32,
+    height: u32,
+}
+
+fn page_xhtml(page: &EpubPage) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <title>{image}</title>
+  <meta name="viewport" content="width={width}, height={height}"/>
+  <style>html, body {{ margin: 0; padding: 0; }} img {{ display: block; width: {width}px; height: {height}px; }}</style>
+</head>
+<body><img src="images/{image}" alt=""/></body>
+</html>
+"#,
+        image = page.image,
+        width = page.width,
+        height = page.height
+    )
+}
+
+fn nav_xhtml(title: &str, toc: &[(String, usize)]) -> String {
+    let items: String = toc
+        .iter()
+        .map(|(title, page)| {
+            format!(
+                "      <li><a href=\"page{:04}.xhtml\">{}</a></li>\n",
+                page,
+                escape_xml(title)
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>{title}</title></head>
+<body>
+  <nav epub:type="toc" id="toc">
+    <h1>{title}</h1>
+    <ol>
+{items}    </ol>
+  </nav>
+</body>
+</html>
+"#,
+        title = escape_xml(title),
+        items = items
+    )
+}
+
+fn content_opf(identifier: &str, title: &str, author: Option<&str>, pages: &[EpubPage]) -> String {
+    let mut manifest = String::from(
+        "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n",
+    );
+    let mut spine = String::new();
+    for (index, page) in pages.iter().enumerate() {
+        let extension = page.image.rsplit('.').next().unwrap_or_default();
+        manifest.push_str(&format!(
+            "    <item id=\"image{0:04}\" href=\"images/{1}\" media-type=\"{2}\"{3}/>\n",
+            index,
+            page.image,
+            media_type(extension),
+            if index == 0 {
+                " properties=\"cover-image\""
+            } else {
+                ""
+            }
+        ));
+        manifest.push_str(&format!(
+            "    <item id=\"page{0:04}\" href=\"page{0:04}.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+            index
+        ));
+        spine.push_str(&format!("    <itemref idref=\"page{:04}\"/>\n", index));
+    }
+    let creator = author
+        .map(|author| format!("    <dc:creator>{}</dc:creator>\n", escape_xml(author)))
+        .unwrap_or_default();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="id" prefix="rendition: http://www.idpf.org/vocab/rendition/#">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="id">{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>
+{creator}    <dc:language>und</dc:language>
+    <meta property="dcterms:modified">{modified}</meta>
+    <meta property="rendition:layout">pre-paginated</meta>
+    <meta property="rendition:spread">none</meta>
+  </metadata>
+  <manifest>
+{manifest}  </manifest>
+  <spine>
+{spine}  </spine>
+</package>
+"#,
+        identifier = escape_xml(identifier),
+        title = escape_xml(title),
+        creator = creator,
+        modified = Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+        manifest = manifest,
+        spine = spine
+    )
+}
+
+/// Writes a fixed layout EPUB 3 with one page per image and a table of
+/// contents entry per chapter.
+pub fn write_epub(title: &str, chapters: &[VolumeChapter], output: &Path) -> Result<(), Error> {
+    let mut pages = Vec::new();
+    let mut toc = Vec::new();
+    for chapter in chapters {
+        // Its entry would point at the next chapter, or past the last page.
+        if chapter.pages.is_empty() {
+            continue;
+        }
+        toc.push((chapter.chapter.title.to_string(), pages.len()));
+        for file in &chapter.pages {
+            let (width, height) = image::io::Reader::new(Cursor::new(file.read()?))
+                .with_guessed_format()
+                .map_err(map_to_string(Error::IoError))?
+                .into_dimensions()
+                .map_err(map_to_string(Error::IoError))?;
+            let extension = file.extension().unwrap_or_else(|| "jpg".to_string());
+            pages.push(EpubPage {
+                file,
+                image: format!("{:04}.{}", pages.len(), extension),
+                width,
+                height,
+            });
+        }
+    }
+    if pages.is_empty() {
+        return Err(Error::IoError("No pages to export".to_string()));
+    }
+
+    let manga = &chapters[0].chapter.manga;
+    let author = manga
+        .details
+        .as_ref()
+        .and_then(|details| details.authors.front())
+        .map(|author| author.as_ref());
+    let identifier = format!("{}#{}", manga.url, title);
+
+    write_atomic(output, |file| {
+        let mut zip = ZipWriter::new(file);
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default();
+
+        // The mimetype must come first and uncompressed.
+        zip.start_file("mimetype", stored)
+            .map_err(map_to_string(Error::IoError))?;
+        zip.write_all(b"application/epub+zip")
+            .map_err(map_to_string(Error::IoError))?;
+        zip.start_file("META-INF/container.xml", deflated)
+            .map_err(map_to_string(Error::IoError))?;
+        zip.write_all(CONTAINER.as_bytes())
+            .map_err(map_to_string(Error::IoError))?;
+        zip.start_file("OEBPS/content.opf", deflated)
+            .map_err(map_to_string(Error::IoError))?;
+        zip.write_all(content_opf(&identifier, title, author, &pages).as_bytes())
+            .map_err(map_to_string(Error::IoError))?;
+        zip.start_file("OEBPS/nav.xhtml", deflated)
+            .map_err(map_to_string(Error::IoError))?;
+        zip.write_all(nav_xhtml(title, &toc).as_bytes())
+            .map_err(map_to_string(Error::IoError))?;
+        for (index, page) in pages.iter().enumerate() {
+            zip.start_file(format!("OEBPS/page{:04}.xhtml", index), deflated)
+                .map_err(map_to_string(Error::IoError))?;
+            zip.write_all(page_xhtml(page).as_bytes())
+                .map_err(map_to_string(Error::IoError))?;
+            zip.start_file(format!("OEBPS/images/{}", page.image), stored)
+                .map_err(map_to_string(Error::IoError))?;
+            zip.write_all(&page.file.read()?)
+                .map_err(map_to_string(Error::IoError))?;
+        }
+        zip.finish().map_err(map_to_string(Error::IoError))?;
+        Ok(())
+    })
+}
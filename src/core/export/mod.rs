@@ -0,0 +1,214 @@
+//! Bundles downloaded chapters into files for other readers.
+
+pub mod cbz;
+pub mod epub;
+pub mod pdf;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use druid::Data;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    error::{map_to_string, Error},
+    websites::local::{
+        archive_metadata_path, archive_pages, page_files, read_archive_page, METADATA_FILENAME,
+    },
+    Chapter,
+};
+
+/// How downloaded chapters are stored.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize, Default)]
+pub enum OutputFormat {
+    /// Loose page images in a folder per chapter.
+    #[default]
+    Folder,
+    /// A `.cbz` archive per chapter, its `metadata.json` kept next to it.
+    Cbz,
+    Both,
+}
+
+/// File format of chapters bundled into one volume.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data)]
+pub enum VolumeFormat {
+    /// Fixed layout EPUB 3.
+    Epub,
+    Pdf,
+}
+
+impl VolumeFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Epub => "epub",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+impl std::fmt::Display for VolumeFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Epub => "EPUB",
+            Self::Pdf => "PDF",
+        })
+    }
+}
+
+/// A page image of a downloaded chapter.
+pub enum PageFile {
+    Loose(PathBuf),
+    /// Entry `name` of the `.cbz` at `archive`.
+    Archived {
+        archive: PathBuf,
+        name: String,
+    },
+}
+
+impl PageFile {
+    /// Lowercase extension of the image file.
+    pub fn extension(&self) -> Option<String> {
+        let name = match self {
+            Self::Loose(path) => path.file_name()?.to_str()?,
+            Self::Archived { name, .. } => name.as_str(),
+        };
+        Path::new(name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+    }
+
+    pub fn read(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Loose(path) => fs::read(path).map_err(map_to_string(Error::IoError)),
+            Self::Archived { archive, name } => read_archive_page(archive, name),
+        }
+    }
+}
+
+/// Pages of the chapter downloaded to the folder `dir`, or archived next to
+/// it when the folder is gone.
+pub fn downloaded_pages(dir: &Path) -> Result<Vec<PageFile>, Error> {
+    if dir.is_dir() {
+        return Ok(page_files(dir)?.into_iter().map(PageFile::Loose).collect());
+    }
+    let archive = cbz_path(dir);
+    Ok(archive_pages(&archive)?
+        .into_iter()
+        .map(|name| PageFile::Archived {
+            archive: archive.clone(),
+            name,
+        })
+        .collect())
+}
+
+/// A downloaded chapter and its page files, in order.
+pub struct VolumeChapter {
+    pub chapter: Chapter,
+    pub pages: Vec<PageFile>,
+}
+
+/// `Manga Ch. 10-20`, or chapter titles when they are not numbered.
+pub fn volume_title(chapters: &[Chapter]) -> String {
+    let (first, last) = match (chapters.first(), chapters.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return String::new(),
+    };
+    let range = match (first.number, last.number) {
+        (Some(first), Some(last)) if first == last => format!("Ch. {}", first),
+        (Some(first), Some(last)) => format!("Ch. {}-{}", first, last),
+        _ if chapters.len() == 1 => first.title.to_string(),
+        _ => format!("{} - {}", first.title, last.title),
+    };
+    format!("{} {}", first.manga.title, range)
+}
+
+/// Bundles chapters downloaded to the given folders, or archived next to
+/// them, into one volume file named `title`, in the order given.
+pub fn export_volume(
+    title: &str,
+    chapters: &[(Chapter, PathBuf)],
+    format: VolumeFormat,
+    output: &Path,
+) -> Result<(), Error> {
+    let chapters = chapters
+        .iter()
+        .map(|(chapter, dir)| {
+            Ok(VolumeChapter {
+                chapter: chapter.clone(),
+                pages: downloaded_pages(dir)?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    match format {
+        VolumeFormat::Epub => epub::write_epub(title, &chapters, output),
+        VolumeFormat::Pdf => pdf::write_pdf(title, &chapters, output),
+    }
+}
+
+/// The `.cbz` archive of a chapter downloaded to `dir`, next to the folder.
+pub fn cbz_path(dir: &Path) -> PathBuf {
+    let mut path = dir.as_os_str().to_owned();
+    path.push(".cbz");
+    PathBuf::from(path)
+}
+
+/// Converts the chapter downloaded to `dir` to `format`. The folder is removed
+/// once archived unless it is kept too, its `metadata.json` moving next to the
+/// archive.
+pub fn export_chapter(chapter: &Chapter, dir: &Path, format: OutputFormat) -> Result<(), Error> {
+    if format == OutputFormat::Folder {
+        return Ok(());
+    }
+    let pages = page_files(dir)?;
+    let archive = cbz_path(dir);
+    cbz::write_cbz(chapter, &pages, &archive)?;
+    if format == OutputFormat::Cbz {
+        fs::rename(dir.join(METADATA_FILENAME), archive_metadata_path(&archive))
+            .map_err(map_to_string(Error::IoError))?;
+        fs::remove_dir_all(dir).map_err(map_to_string(Error::IoError))?;
+    }
+    Ok(())
+}
+
+/// Escapes text for xml content and attribute values.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape_xml`].
+pub fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Writes `output` through a temporary file next to it, so an interrupted
+/// export never leaves a truncated file behind.
+pub fn write_atomic(
+    output: &Path,
+    write: impl FnOnce(fs::File) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut temp = output.as_os_str().to_owned();
+    temp.push(".part");
+    let file = fs::File::create(&temp).map_err(map_to_string(Error::IoError))?;
+    if let Err(err) = write(file) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    fs::rename(&temp, output).map_err(map_to_string(Error::IoError))
+}
@@ -0,0 +1,262 @@
+use std::{
+    io::{self, Cursor, Write},
+    path::Path,
+};
+
+use druid::image::{self, codecs::jpeg::JpegDecoder, ColorType, ImageDecoder};
+use flate2::{write::ZlibEncoder, Compression};
+
+use crate::core::error::{map_to_string, Error};
+
+use super::{write_atomic, VolumeChapter};
+
+/// Page image data as embedded in the file.
+struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    filter: &'static str,
+    data: Vec<u8>,
+}
+
+impl PdfImage {
+    /// JPEGs are embedded as they are, anything else is decoded and
+    /// deflated.
+    fn load(bytes: Vec<u8>) -> Result<Self, Error> {
+        if bytes.starts_with(&[0xFF, 0xD8]) {
+            if let Ok(decoder) = JpegDecoder::new(Cursor::new(&bytes)) {
+                let (width, height) = decoder.dimensions();
+                let color_space = match decoder.color_type() {
+                    ColorType::L8 => Some("/DeviceGray"),
+                    ColorType::Rgb8 => Some("/DeviceRGB"),
+                    _ => None,
+                };
+                if let Some(color_space) = color_space {
+                    return Ok(Self {
+                        width,
+                        height,
+                        color_space,
+                        filter: "/DCTDecode",
+                        data: bytes,
+                    });
+                }
+            }
+        }
+        let image = image::load_from_memory(&bytes)
+            .map_err(map_to_string(Error::IoError))?
+            .to_rgb8();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(image.as_raw())
+            .map_err(map_to_string(Error::IoError))?;
+        Ok(Self {
+            width: image.width(),
+            height: image.height(),
+            color_space: "/DeviceRGB",
+            filter: "/FlateDecode",
+            data: encoder.finish().map_err(map_to_string(Error::IoError))?,
+        })
+    }
+}
+
+/// Text string as UTF-16BE hex, which every reader displays whatever the
+/// script.
+fn text_string(text: &str) -> String {
+    let mut string = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        string.push_str(&format!("{:04X}", unit));
+    }
+    string.push('>');
+    string
+}
+
+/// Writes numbered objects, keeping their offsets for the cross-reference
+/// table.
+struct PdfWriter<W> {
+    out: W,
+    position: usize,
+    offsets: Vec<usize>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn new(out: W, object_count: usize) -> Self {
+        Self {
+            out,
+            position: 0,
+            offsets: vec![0; object_count],
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    fn object(&mut self, id: usize, body: &str) -> io::Result<()> {
+        self.offsets[id - 1] = self.position;
+        self.write(format!("{} 0 obj\n{}\nendobj\n", id, body).as_bytes())
+    }
+
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) -> io::Result<()> {
+        self.offsets[id - 1] = self.position;
+        self.write(
+            format!(
+                "{} 0 obj\n<< {} /Length {} >>\nstream\n",
+                id,
+                dict,
+                data.len()
+            )
+            .as_bytes(),
+        )?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")
+    }
+
+    fn finish(mut self, root: usize, info: usize) -> io::Result<()> {
+        let xref = self.position;
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            table.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        table.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            root,
+            info,
+            xref
+        ));
+        self.write(table.as_bytes())?;
+        self.out.flush()
+    }
+}
+
+const CATALOG: usize = 1;
+const PAGES: usize = 2;
+const OUTLINES: usize = 3;
+const INFO: usize = 4;
+const FIRST_PAGE: usize = 5;
+
+/// Objects of the page at `index`: the page, its content stream and its
+/// image.
+fn page_objects(index: usize) -> (usize, usize, usize) {
+    let page = FIRST_PAGE + index * 3;
+    (page, page + 1, page + 2)
+}
+
+/// Writes a PDF with one page per image, each page the size of its image, and
+/// an outline entry per chapter.
+pub fn write_pdf(title: &str, chapters: &[VolumeChapter], output: &Path) -> Result<(), Error> {
+    let page_count: usize = chapters.iter().map(|chapter| chapter.pages.len()).sum();
+    if page_count == 0 {
+        return Err(Error::IoError("No pages to export".to_string()));
+    }
+    let first_outline = FIRST_PAGE + page_count * 3;
+    let object_count = first_outline + chapters.len() - 1;
+    let manga = &chapters[0].chapter.manga;
+    let author = manga
+        .details
+        .as_ref()
+        .and_then(|details| details.authors.front())
+        .map(|author| format!(" /Author {}", text_string(author)))
+        .unwrap_or_default();
+
+    write_atomic(output, |file| {
+        let io_error = map_to_string(Error::IoError);
+        let mut pdf = PdfWriter::new(io::BufWriter::new(file), object_count);
+        pdf.write(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n")
+            .map_err(&io_error)?;
+
+        pdf.object(
+            CATALOG,
+            &format!(
+                "<< /Type /Catalog /Pages {} 0 R /Outlines {} 0 R /PageMode /UseOutlines >>",
+                PAGES, OUTLINES
+            ),
+        )
+        .map_err(&io_error)?;
+        let kids: Vec<String> = (0..page_count)
+            .map(|index| format!("{} 0 R", page_objects(index).0))
+            .collect();
+        pdf.object(
+            PAGES,
+            &format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                page_count
+            ),
+        )
+        .map_err(&io_error)?;
+        pdf.object(
+            OUTLINES,
+            &format!(
+                "<< /Type /Outlines /First {} 0 R /Last {} 0 R /Count {} >>",
+                first_outline,
+                first_outline + chapters.len() - 1,
+                chapters.len()
+            ),
+        )
+        .map_err(&io_error)?;
+        pdf.object(
+            INFO,
+            &format!(
+                "<< /Title {}{} /Producer (Mangetsu) >>",
+                text_string(title),
+                author
+            ),
+        )
+        .map_err(&io_error)?;
+
+        let mut index = 0;
+        for (number, chapter) in chapters.iter().enumerate() {
+            let outline = first_outline + number;
+            let mut links = String::new();
+            if number > 0 {
+                links.push_str(&format!(" /Prev {} 0 R", outline - 1));
+            }
+            if number + 1 < chapters.len() {
+                links.push_str(&format!(" /Next {} 0 R", outline + 1));
+            }
+            // Chapters without pages point at the next page there is.
+            let target = page_objects(index.min(page_count - 1)).0;
+            pdf.object(
+                outline,
+                &format!(
+                    "<< /Title {} /Parent {} 0 R{} /Dest [{} 0 R /Fit] >>",
+                    text_string(&chapter.chapter.title),
+                    OUTLINES,
+                    links,
+                    target
+                ),
+            )
+            .map_err(&io_error)?;
+
+            for file in &chapter.pages {
+                let image = PdfImage::load(file.read()?)?;
+                let (page, contents, xobject) = page_objects(index);
+                pdf.object(
+                    page,
+                    &format!(
+                        "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                        PAGES, image.width, image.height, xobject, contents
+                    ),
+                )
+                .map_err(&io_error)?;
+                let draw = format!("q {} 0 0 {} 0 0 cm /Im0 Do Q", image.width, image.height);
+                pdf.stream(contents, "", draw.as_bytes())
+                    .map_err(&io_error)?;
+                pdf.stream(
+                    xobject,
+                    &format!(
+                        "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent 8 /Filter {}",
+                        image.width, image.height, image.color_space, image.filter
+                    ),
+                    &image.data,
+                )
+                .map_err(&io_error)?;
+                index += 1;
+            }
+        }
+        pdf.finish(CATALOG, INFO).map_err(&io_error)
+    })
+}
@@ -1,24 +1,42 @@
 use std::{path::PathBuf, sync::Arc};
 
+use druid::Data;
+use futures::stream;
 use indexmap::{indexmap, IndexMap};
 use once_cell::sync::OnceCell;
-use reqwest::{header, Client};
+use reqwest::{header, Client, Url};
 use serde::{Deserialize, Serialize};
 
-use crate::core::{cache::Cache, websites::manganel::Manganel, Connector};
+use crate::core::{
+    cache::Cache,
+    error::Error,
+    http::{Http, HttpConfig},
+    websites::{local::Local, mangabox, mangadex::MangaDex, selector::SelectorConnector},
+    Connector, Manga,
+};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+use super::connector::StreamResult;
+
+#[derive(Debug, Clone, Data, Hash, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Connectors {
     Manganel,
+    Mangakakalot,
+    Mangabat,
+    Mangairo,
+    Manganeloinfo,
+    MangaDex,
+    /// Chapters downloaded to the download folder.
+    Local,
+    /// A connector loaded from a user definition, keyed by its id.
+    Custom(Arc<str>),
 }
 
 impl std::fmt::Display for Connectors {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let connector = GlobalAPI::global()
-            .connectors
-            .get(self)
-            .expect("Connector Not Found");
-        f.write_str(connector.get_connector_info().label)
+        match GlobalAPI::global().connectors.get(self) {
+            Some(connector) => f.write_str(&connector.get_connector_info().label),
+            None => write!(f, "{:?}", self),
+        }
     }
 }
 
@@ -29,16 +47,36 @@ type Value = Box<dyn Connector + Send + Sync>;
 #[derive(Debug)]
 pub struct GlobalAPI {
     pub connectors: IndexMap<Connectors, Value>,
-    pub client: Client,
+    pub http: Http,
     pub cache: Cache,
 }
 
 impl GlobalAPI {
-    pub fn install(cache_base: Option<PathBuf>) {
-        let connectors = indexmap! {
-            Connectors::Manganel => Box::new(Manganel::new()) as Value,
+    pub fn install(
+        cache_base: Option<PathBuf>,
+        connectors_dir: Option<PathBuf>,
+        download_dir: Option<PathBuf>,
+        languages: Vec<String>,
+        http: HttpConfig,
+    ) {
+        let mut connectors = indexmap! {
+            Connectors::Manganel => Box::new(mangabox::manganato()) as Value,
+            Connectors::Mangakakalot => Box::new(mangabox::mangakakalot()) as Value,
+            Connectors::Mangabat => Box::new(mangabox::mangabat()) as Value,
+            Connectors::Mangairo => Box::new(mangabox::mangairo()) as Value,
+            Connectors::Manganeloinfo => Box::new(mangabox::manganeloinfo()) as Value,
+            Connectors::MangaDex => Box::new(MangaDex::new(languages)) as Value,
+            Connectors::Local => Box::new(Local::new(download_dir)) as Value,
         };
 
+        if let Some(dir) = connectors_dir {
+            for connector in SelectorConnector::load_dir(&dir) {
+                connectors
+                    .entry(connector.connector())
+                    .or_insert_with(|| Box::new(connector) as Value);
+            }
+        }
+
         let mut headers = reqwest::header::HeaderMap::new();
 
         headers.insert(header::ACCEPT, "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.9".parse().unwrap());
@@ -46,12 +84,38 @@ impl GlobalAPI {
         GLOBAL_API
             .set(Arc::new(GlobalAPI {
                 connectors,
-                client: Client::builder().default_headers(headers).build().unwrap(),
+                http: Http::new(
+                    Client::builder().default_headers(headers).build().unwrap(),
+                    http,
+                ),
                 cache: Cache::new(cache_base),
             }))
             .unwrap();
     }
 
+    /// Searches every installed connector at once, results are interleaved as
+    /// each connector yields them.
+    pub fn search(&self, query: &str, page: i32) -> StreamResult<Manga> {
+        Box::pin(stream::select_all(
+            self.connectors
+                .values()
+                .map(|connector| connector.search(query.to_string(), page)),
+        ))
+    }
+
+    pub fn connector(&self, connector: &Connectors) -> Result<&Value, Error> {
+        self.connectors.get(connector).ok_or(Error::NotFound)
+    }
+
+    /// Finds the first connector able to handle `url`.
+    pub fn connector_for_url(&self, url: &str) -> Result<&Value, Error> {
+        let url = Url::parse(url).map_err(|err| Error::RequestFail(err.to_string()))?;
+        self.connectors
+            .values()
+            .find(|connector| connector.can_handle_uri(url.clone()))
+            .ok_or(Error::NotFound)
+    }
+
     pub fn global<'a>() -> &'a GlobalAPI {
         GLOBAL_API
             .get()
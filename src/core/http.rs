@@ -0,0 +1,298 @@
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{header, Client, IntoUrl, Request, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{self, Instant},
+};
+
+use crate::core::{error::Error, GlobalAPI};
+
+/// How hard a single host may be hit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HostLimit {
+    /// Requests in flight at once, bodies included.
+    pub concurrency: usize,
+    pub requests_per_second: f64,
+}
+
+impl Default for HostLimit {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            requests_per_second: 4.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub default_limit: HostLimit,
+    /// Overrides keyed by host name, e.g. `api.mangadex.org`.
+    pub hosts: HashMap<String, HostLimit>,
+    pub max_retries: u32,
+    pub backoff_base_ms: u64,
+    pub backoff_max_ms: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            default_limit: Default::default(),
+            hosts: HashMap::new(),
+            max_retries: 4,
+            backoff_base_ms: 500,
+            backoff_max_ms: 30_000,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Host {
+    permits: Arc<Semaphore>,
+    interval: Duration,
+    /// Earliest instant the next request may start.
+    next_slot: Mutex<Instant>,
+}
+
+impl Host {
+    fn new(limit: HostLimit) -> Self {
+        let interval = if limit.requests_per_second > 0. {
+            Duration::from_secs_f64(1. / limit.requests_per_second)
+        } else {
+            Duration::ZERO
+        };
+        Self {
+            permits: Arc::new(Semaphore::new(limit.concurrency.max(1))),
+            interval,
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait_turn(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        time::sleep_until(slot).await;
+    }
+
+    /// Holds every request to this host back until `until`, used for `Retry-After`.
+    fn pause_until(&self, until: Instant) {
+        let mut next_slot = self.next_slot.lock().unwrap();
+        *next_slot = (*next_slot).max(until);
+    }
+}
+
+/// A response holding on to its host permit until the body is consumed.
+#[derive(Debug)]
+pub struct Response {
+    inner: reqwest::Response,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Response {
+    /// Maps error statuses to [`Error::NotFound`] or [`Error::HttpStatus`].
+    pub fn error_for_status(self) -> Result<Self, Error> {
+        match self.inner.status() {
+            StatusCode::NOT_FOUND => Err(Error::NotFound),
+            status if !status.is_success() => Err(Error::HttpStatus(status.as_u16())),
+            _ => Ok(self),
+        }
+    }
+
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, Error> {
+        self.inner
+            .chunk()
+            .await
+            .map_err(|err| Error::RequestFail(err.to_string()))
+    }
+
+    pub async fn bytes(self) -> Result<Bytes, Error> {
+        self.inner
+            .bytes()
+            .await
+            .map_err(|err| Error::RequestFail(err.to_string()))
+    }
+
+    pub async fn text(self) -> Result<String, Error> {
+        self.inner
+            .text()
+            .await
+            .map_err(|err| Error::RequestFail(err.to_string()))
+    }
+}
+
+impl Deref for Response {
+    type Target = reqwest::Response;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// The request layer every connector and download goes through: per host
+/// concurrency and rate limits, and retries of transient failures.
+#[derive(Debug)]
+pub struct Http {
+    client: Client,
+    config: HttpConfig,
+    hosts: Mutex<HashMap<String, Arc<Host>>>,
+}
+
+impl Http {
+    pub fn new(client: Client, config: HttpConfig) -> Self {
+        Self {
+            client,
+            config,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    fn host(&self, name: &str) -> Arc<Host> {
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let limit = self
+                    .config
+                    .hosts
+                    .get(name)
+                    .copied()
+                    .unwrap_or(self.config.default_limit);
+                Arc::new(Host::new(limit))
+            })
+            .clone()
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .config
+            .backoff_base_ms
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.config.backoff_max_ms);
+        // Equal jitter: between half and all of the delay.
+        Duration::from_millis(rand::thread_rng().gen_range(delay / 2..=delay))
+    }
+
+    /// Sends `request`, retrying timeouts, connection errors and `408`, `429`
+    /// and `5xx` statuses. The last response is returned whatever its status,
+    /// as is one asking to retry after more than `backoff_max_ms`.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let request = request
+            .build()
+            .map_err(|err| Error::RequestFail(err.to_string()))?;
+        let host = self.host(request.url().host_str().unwrap_or_default());
+
+        let mut attempt = 0;
+        let mut next: Option<Request> = Some(request);
+        loop {
+            let request = next.take().expect("request consumed without retry");
+            next = if attempt < self.config.max_retries {
+                request.try_clone()
+            } else {
+                None
+            };
+
+            let permit = host
+                .permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("host semaphore closed");
+            host.wait_turn().await;
+
+            let url = request.url().clone();
+            let delay = match self.client.execute(request).await {
+                Ok(response) if !is_transient(response.status()) || next.is_none() => {
+                    return Ok(Response {
+                        inner: response,
+                        _permit: permit,
+                    })
+                }
+                Ok(response) => {
+                    let delay = match retry_after(&response) {
+                        // Longer than any backoff, the caller gets the status instead.
+                        Some(delay)
+                            if delay > Duration::from_millis(self.config.backoff_max_ms) =>
+                        {
+                            log::warn!(
+                                "{} returned {} and asks to wait {:?}, giving up",
+                                url,
+                                response.status(),
+                                delay
+                            );
+                            return Ok(Response {
+                                inner: response,
+                                _permit: permit,
+                            });
+                        }
+                        Some(delay) => {
+                            host.pause_until(Instant::now() + delay);
+                            delay
+                        }
+                        None => self.backoff(attempt),
+                    };
+                    log::warn!("{} returned {}, retrying", url, response.status());
+                    delay
+                }
+                Err(err) if (err.is_timeout() || err.is_connect()) && next.is_some() => {
+                    log::warn!("{} failed: {}, retrying", url, err);
+                    self.backoff(attempt)
+                }
+                Err(err) => return Err(Error::RequestFail(err.to_string())),
+            };
+
+            drop(permit);
+            time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends `request` and reads the body as text, see [`Response::error_for_status`].
+    pub async fn text(&self, request: RequestBuilder) -> Result<String, Error> {
+        self.send(request).await?.error_for_status()?.text().await
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+/// Reads `Retry-After`, given either in seconds or as an http date.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// Shorthand for [`Http::text`] on the global request layer.
+pub async fn fetch_text(request: RequestBuilder) -> Result<String, Error> {
+    GlobalAPI::global().http.text(request).await
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
-use druid::{Data, Lens};
+use druid::{im::Vector, Data, Lens};
+use futures::{future, stream};
 use serde::{Deserialize, Serialize};
 
 use super::{connector::StreamResult, Chapter, Connectors, GlobalAPI};
@@ -11,15 +12,58 @@ pub struct Manga {
     pub title: Arc<str>,
     pub icon_url: Arc<str>,
     pub connector: Connectors,
+    #[serde(default)]
+    pub details: Option<MangaDetails>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Data, Lens, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MangaDetails {
+    pub alt_titles: Vector<Arc<str>>,
+    pub authors: Vector<Arc<str>>,
+    pub genres: Vector<Arc<str>>,
+    pub status: MangaStatus,
+    pub last_updated: Option<Arc<str>>,
+    pub description: Option<Arc<str>>,
+}
+
+#[derive(Copy, Clone, Debug, Data, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub enum MangaStatus {
+    Ongoing,
+    Completed,
+    #[default]
+    Unknown,
+}
+
+impl MangaStatus {
+    pub fn parse(status: &str) -> Self {
+        let status = status.trim().to_lowercase();
+        if status.starts_with("ongoing") {
+            Self::Ongoing
+        } else if status.starts_with("complete") {
+            Self::Completed
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+impl std::fmt::Display for MangaStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Ongoing => "Ongoing",
+            Self::Completed => "Completed",
+            Self::Unknown => "Unknown",
+        })
+    }
 }
 
 impl Manga {
-    pub fn get_chapters(&self) -> StreamResult<Chapter> {
-        GlobalAPI::global()
-            .connectors
-            .get(&self.connector)
-            .unwrap()
-            .get_chapters(self.clone())
+    pub fn get_chapters(&self) -> StreamResult<'static, Chapter> {
+        match GlobalAPI::global().connector(&self.connector) {
+            Ok(connector) => connector.get_chapters(self.clone()),
+            Err(err) => Box::pin(stream::once(future::ready(Err(err)))),
+        }
     }
 }
 
@@ -2,7 +2,9 @@ pub mod cache;
 mod chapter;
 mod connector;
 pub mod error;
+pub mod export;
 mod global_api;
+pub mod http;
 mod manga;
 mod page;
 mod websites;
@@ -10,5 +12,6 @@ mod websites;
 pub use chapter::Chapter;
 pub use connector::{Connector, ConnectorInfo};
 pub use global_api::{Connectors, GlobalAPI};
-pub use manga::Manga;
+pub use manga::{Manga, MangaDetails, MangaStatus};
 pub use page::Page;
+pub use websites::local::{self, page_files};
@@ -1,8 +1,10 @@
 use std::sync::Arc;
 
+use druid::Data;
+
 use crate::core::Connectors;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Data)]
 pub struct Page {
     pub url: Arc<str>,
     pub referer: Arc<str>,
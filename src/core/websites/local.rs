@@ -0,0 +1,402 @@
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use futures::{stream, StreamExt};
+use reqwest::Url;
+use zip::ZipArchive;
+
+use crate::core::{
+    connector::{FutureResult, StreamResult},
+    error::{map_to_string, Error},
+    export::cbz::{read_comic_info, COMIC_INFO_FILENAME},
+    Chapter, Connector, ConnectorInfo, Connectors, Manga, Page,
+};
+
+pub const METADATA_FILENAME: &str = "metadata.json";
+const PAGE_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "gif", "webp"];
+/// File name of the cover saved in a downloaded manga folder, before its
+/// extension.
+pub const COVER_STEM: &str = "cover";
+
+/// Serves downloaded chapters from the download folder, where each manga and
+/// chapter folder holds the `metadata.json` written when downloading.
+/// Urls are `file://` urls of those folders and pages. Chapters kept as a
+/// `.cbz` only are the url of the archive, their pages that url with the
+/// entry name as fragment.
+pub struct Local {
+    info: ConnectorInfo,
+    dir: Option<PathBuf>,
+}
+
+impl Local {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            info: ConnectorInfo {
+                id: "local".to_string(),
+                label: "Downloaded".to_string(),
+                tags: vec![],
+                url: dir
+                    .as_deref()
+                    .and_then(|dir| Url::from_directory_path(dir).ok())
+                    .unwrap_or_else(|| Url::parse("file:///").unwrap()),
+            },
+            dir,
+        }
+    }
+
+    fn path(url: &str) -> Result<PathBuf, Error> {
+        Url::parse(url)
+            .ok()
+            .filter(|url| url.scheme() == "file")
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or(Error::NotFound)
+    }
+
+    fn url(path: &Path) -> Result<Url, Error> {
+        Url::from_directory_path(path)
+            .map_err(|_| Error::IoError(format!("Invalid path {:?}", path)))
+    }
+
+    /// Reads the manga downloaded to `dir`, as a local manga. Its saved cover
+    /// is used when there is one, the remote one otherwise.
+    fn manga(dir: &Path) -> Result<Manga, Error> {
+        let manga: Manga = read_metadata(dir)?;
+        let icon_url = cover_file(dir)
+            .and_then(|path| Url::from_file_path(path).ok())
+            .map(|url| url.as_str().into())
+            .unwrap_or_else(|| manga.icon_url.clone());
+        Ok(Manga {
+            url: Self::url(dir)?.as_str().into(),
+            icon_url,
+            connector: Connectors::Local,
+            ..manga
+        })
+    }
+
+    fn mangas(dir: Option<&Path>) -> Result<Vec<Manga>, Error> {
+        let dir = dir.ok_or_else(|| Error::IoError("Failed to get Download Path".to_string()))?;
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        Ok(metadata_folders(dir)?
+            .iter()
+            .filter_map(|dir| match Self::manga(dir) {
+                Ok(manga) => Some(manga),
+                Err(err) => {
+                    log::warn!("skipping {:?}: {}", dir, err);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    fn chapters(manga: &Manga) -> Result<Vec<Chapter>, Error> {
+        let dir = Self::path(&manga.url)?;
+        let mut chapters = Vec::new();
+        for dir in metadata_folders(&dir)? {
+            let chapter: Chapter = match read_metadata(&dir) {
+                Ok(chapter) => chapter,
+                Err(err) => {
+                    log::warn!("skipping {:?}: {}", dir, err);
+                    continue;
+                }
+            };
+            chapters.push(Chapter {
+                url: Self::url(&dir)?.as_str().into(),
+                connector: Connectors::Local,
+                manga: manga.clone(),
+                ..chapter
+            });
+        }
+        for archive in archive_files(&dir) {
+            if archive.with_extension("").is_dir() {
+                continue;
+            }
+            let chapter = match read_archive_metadata(&archive, manga) {
+                Ok(chapter) => chapter,
+                Err(err) => {
+                    log::warn!("skipping {:?}: {}", archive, err);
+                    continue;
+                }
+            };
+            let url = Url::from_file_path(&archive)
+                .map_err(|_| Error::IoError(format!("Invalid path {:?}", archive)))?;
+            chapters.push(Chapter {
+                url: url.as_str().into(),
+                connector: Connectors::Local,
+                manga: manga.clone(),
+                ..chapter
+            });
+        }
+        // Newest first, as websites list them.
+        chapters.sort_by(|a, b| {
+            b.number
+                .partial_cmp(&a.number)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(chapters)
+    }
+
+    fn pages(chapter: &Chapter) -> Result<Vec<Page>, Error> {
+        let path = Self::path(&chapter.url)?;
+        let urls = if is_archive(&path) {
+            let url = Url::from_file_path(&path)
+                .map_err(|_| Error::IoError(format!("Invalid path {:?}", path)))?;
+            archive_pages(&path)?
+                .into_iter()
+                .map(|name| {
+                    let mut url = url.clone();
+                    url.set_fragment(Some(&name));
+                    url
+                })
+                .collect()
+        } else {
+            page_files(&path)?
+                .into_iter()
+                .map(|path| {
+                    Url::from_file_path(&path)
+                        .map_err(|_| Error::IoError(format!("Invalid path {:?}", path)))
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+        Ok(urls
+            .into_iter()
+            .map(|url| Page {
+                url: url.as_str().into(),
+                referer: chapter.url.clone(),
+                connector: Connectors::Local,
+            })
+            .collect())
+    }
+}
+
+/// Reads the `metadata.json` of a downloaded manga or chapter folder, as
+/// written when downloading.
+pub fn read_metadata<T: serde::de::DeserializeOwned>(dir: &Path) -> Result<T, Error> {
+    let file = File::open(dir.join(METADATA_FILENAME)).map_err(map_to_string(Error::IoError))?;
+    serde_json::from_reader(file).map_err(map_to_string(Error::IoError))
+}
+
+/// Where the `metadata.json` of a chapter kept as the `.cbz` at `archive` is
+/// moved to, next to it.
+pub fn archive_metadata_path(archive: &Path) -> PathBuf {
+    archive.with_extension("json")
+}
+
+/// Reads the chapter archived at `archive`, from the `metadata.json` next to
+/// it or else from its `ComicInfo.xml`.
+pub fn read_archive_metadata(archive: &Path, manga: &Manga) -> Result<Chapter, Error> {
+    if let Ok(file) = File::open(archive_metadata_path(archive)) {
+        return serde_json::from_reader(file).map_err(map_to_string(Error::IoError));
+    }
+    let xml = String::from_utf8(read_archive_page(archive, COMIC_INFO_FILENAME)?)
+        .map_err(map_to_string(Error::IoError))?;
+    read_comic_info(&xml, manga)
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("cbz"))
+}
+
+/// `.cbz` files below `dir`.
+pub fn archive_files(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut files = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.is_dir() {
+            files.extend(archive_files(&path));
+        } else if is_archive(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+/// The cover saved in the downloaded manga folder `dir`, if any.
+pub fn cover_file(dir: &Path) -> Option<PathBuf> {
+    PAGE_EXTENSIONS
+        .iter()
+        .map(|extension| dir.join(format!("{}.{}", COVER_STEM, extension)))
+        .find(|path| path.is_file())
+}
+
+/// Folders below `dir` holding a `metadata.json`, looking into the others as
+/// download templates may nest them.
+pub fn metadata_folders(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut folders = Vec::new();
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(map_to_string(Error::IoError))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.join(METADATA_FILENAME).is_file() {
+            folders.push(path);
+        } else {
+            folders.extend(metadata_folders(&path)?);
+        }
+    }
+    Ok(folders)
+}
+
+/// Orders `2.jpg` before `10.jpg` by comparing runs of digits as numbers.
+fn natural_key(name: &str) -> Vec<(u64, String)> {
+    let mut key = Vec::new();
+    let mut rest = name;
+    while !rest.is_empty() {
+        let digits = rest.starts_with(|c: char| c.is_ascii_digit());
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+        key.push(if digits {
+            (run.parse().unwrap_or(u64::MAX), String::new())
+        } else {
+            (0, run.to_lowercase())
+        });
+        rest = tail;
+    }
+    key
+}
+
+/// Page files of a downloaded chapter folder, in order. Pages are named
+/// after their number.
+pub fn page_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut pages: Vec<(Vec<(u64, String)>, PathBuf)> = fs::read_dir(dir)
+        .map_err(map_to_string(Error::IoError))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(OsStr::to_str)
+                .map(|extension| PAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+                .unwrap_or(false)
+        })
+        .filter_map(|path| Some((natural_key(path.file_stem()?.to_str()?), path)))
+        .collect();
+    pages.sort();
+    Ok(pages.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Page entries of the `.cbz` at `archive`, in order.
+pub fn archive_pages(archive: &Path) -> Result<Vec<String>, Error> {
+    let file = File::open(archive).map_err(map_to_string(Error::IoError))?;
+    let archive = ZipArchive::new(file).map_err(map_to_string(Error::IoError))?;
+    let mut pages: Vec<(Vec<(u64, String)>, String)> = archive
+        .file_names()
+        .filter(|name| {
+            Path::new(name)
+                .extension()
+                .and_then(OsStr::to_str)
+                .map(|extension| PAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+                .unwrap_or(false)
+        })
+        .map(|name| (natural_key(name), name.to_string()))
+        .collect();
+    pages.sort();
+    Ok(pages.into_iter().map(|(_, name)| name).collect())
+}
+
+/// Reads the entry `name` of the `.cbz` at `archive`.
+pub fn read_archive_page(archive: &Path, name: &str) -> Result<Vec<u8>, Error> {
+    let file = File::open(archive).map_err(map_to_string(Error::IoError))?;
+    let mut archive = ZipArchive::new(file).map_err(map_to_string(Error::IoError))?;
+    let mut entry = archive
+        .by_name(name)
+        .map_err(map_to_string(Error::IoError))?;
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(map_to_string(Error::IoError))?;
+    Ok(bytes)
+}
+
+/// Runs `read` on a blocking task, as reading the download folder blocks.
+async fn blocking<T, F>(read: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+{
+    tokio::task::spawn_blocking(read)
+        .await
+        .map_err(map_to_string(Error::IoError))?
+}
+
+/// Streams the items `read` returns, reading them only once polled.
+fn blocking_stream<T, F>(read: F) -> StreamResult<'static, T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<Vec<T>, Error> + Send + 'static,
+{
+    Box::pin(stream::once(blocking(read)).flat_map(|items| match items {
+        Ok(items) => stream::iter(items.into_iter().map(Ok)).left_stream(),
+        Err(err) => stream::once(async { Err(err) }).right_stream(),
+    }))
+}
+
+impl Connector for Local {
+    fn get_connector_info(&self) -> ConnectorInfo {
+        self.info.clone()
+    }
+
+    fn can_handle_uri(&self, uri: Url) -> bool {
+        uri.scheme() == "file"
+    }
+
+    fn get_manga_from_url(&self, manga_url: Url) -> FutureResult<'_, Manga> {
+        Box::pin(blocking(move || {
+            Self::manga(&Self::path(manga_url.as_str())?)
+        }))
+    }
+
+    fn get_mangas(&self) -> StreamResult<'_, Manga> {
+        let dir = self.dir.clone();
+        blocking_stream(move || Self::mangas(dir.as_deref()))
+    }
+
+    fn get_manga_icon(&self, manga_url: Url) -> FutureResult<'_, Url> {
+        Box::pin(blocking(move || {
+            let manga = Self::manga(&Self::path(manga_url.as_str())?)?;
+            Url::parse(&manga.icon_url).map_err(|err| Error::RequestFail(err.to_string()))
+        }))
+    }
+
+    fn get_mangas_from_page(&self, page: i32) -> StreamResult<'_, Manga> {
+        if page > 1 {
+            return Box::pin(stream::empty());
+        }
+        self.get_mangas()
+    }
+
+    fn search(&self, query: String, page: i32) -> StreamResult<'_, Manga> {
+        let query = query.trim().to_lowercase();
+        if page > 1 || query.is_empty() {
+            return Box::pin(stream::empty());
+        }
+        let dir = self.dir.clone();
+        blocking_stream(move || {
+            Self::mangas(dir.as_deref()).map(|mangas| {
+                mangas
+                    .into_iter()
+                    .filter(|manga| manga.title.to_lowercase().contains(&query))
+                    .collect()
+            })
+        })
+    }
+
+    fn get_chapters(&self, manga: Manga) -> StreamResult<'_, Chapter> {
+        blocking_stream(move || Self::chapters(&manga))
+    }
+
+    fn get_pages(&self, chapter: Chapter) -> StreamResult<'_, Page> {
+        blocking_stream(move || Self::pages(&chapter))
+    }
+}
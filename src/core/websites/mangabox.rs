@@ -0,0 +1,197 @@
+//! The MangaBox family: sites sharing the same engine, and therefore the same
+//! chapter and page markup, but each with its own domains and listing pages.
+
+use regex::Regex;
+use reqwest::Url;
+use scraper::Selector;
+
+use crate::core::{ConnectorInfo, Connectors};
+
+use super::selector::{SelectorConnector, SelectorInfo};
+
+fn info(id: &str, label: &str, url: &str) -> ConnectorInfo {
+    ConnectorInfo {
+        id: id.to_string(),
+        label: label.to_string(),
+        tags: vec![
+            "manga".to_string(),
+            "webtoon".to_string(),
+            "english".to_string(),
+        ],
+        url: Url::parse(url).unwrap(),
+    }
+}
+
+/// Selectors shared by every MangaBox site, laid out like manganato.
+fn base() -> SelectorInfo {
+    SelectorInfo {
+        domain: Regex::new(r"^(www\.|m\.|chap\.)?((chap|read)?manganato|manganelo)\.com$").unwrap(),
+
+        mangas_path: "/genre-all/{page}".to_string(),
+        search_path: Some("/search/story/{query}?page={page}".to_string()),
+        search_separator: "_".to_string(),
+
+        manga_title_filter: Regex::new(r"(?i)(\s+manga|\s+webtoon|\s+others)+\s*$").unwrap(),
+        chapter_title_filter: Regex::new(r"(?i)^\s*(\s+manga|\s+webtoon|\s+others)+").unwrap(),
+        manga_description_filter: Regex::new(r"(?i)^\s*description\s*:").unwrap(),
+        query_manga_title: Selector::parse(
+            "div.container-main div.panel-story-info div.story-info-right h1",
+        )
+        .unwrap(),
+        query_manga_info: Some(
+            Selector::parse(
+                "div.story-info-right table.variations-tableInfo tr, div.story-info-right-extent p",
+            )
+            .unwrap(),
+        ),
+        query_manga_description: Some(Selector::parse("div#panel-story-info-description").unwrap()),
+        query_mangas_page_count: Some(
+            Selector::parse("div.panel-page-number div.group-page a.page-last:last-of-type")
+                .unwrap(),
+        ),
+        query_mangas: Selector::parse("div.genres-item-info h3 a.genres-item-name").unwrap(),
+        query_mangas_icon: Selector::parse("img").unwrap(),
+        query_search_mangas: Some(
+            Selector::parse("div.panel-search-story div.search-story-item a.item-img").unwrap(),
+        ),
+
+        query_icon: Selector::parse(".info-image > img:nth-child(1)").unwrap(),
+
+        query_chapters: Selector::parse(
+            &[
+                "ul.row-content-chapter li", // manganato, mangabat
+                "div.chapter_list ul li",    // mangairo
+                "div.chapter-list div.row",  // mangakakalot(s), kissmangawebsite, manganeloinfo
+                "div.content.mCustomScrollbar div.chapter-list ul li.row div.chapter", // MangaPark
+            ]
+            .join(", "),
+        )
+        .unwrap(),
+        query_chapter_link: Selector::parse("a").unwrap(),
+        query_chapter_views: Some(
+            Selector::parse("span.chapter-view, div.row > span:nth-child(2)").unwrap(),
+        ),
+        query_chapter_date: Some(
+            Selector::parse("span.chapter-time, div.row > span:nth-child(3)").unwrap(),
+        ),
+        chapter_date_formats: vec!["%b %d,%Y %H:%M".to_string(), "%b-%d-%Y %H:%M".to_string()],
+
+        query_pages: Selector::parse(
+            &[
+                "div.container-chapter-reader img", // manganato, mangabat
+                "div.chapter-content div.panel-read-story img", // mangairo
+                "div#vungdoc img, div.vung-doc img, div.vung_doc img", // mangakakalot(s), kissmangawebsite, manganeloinfo
+            ]
+            .join(", "),
+        )
+        .unwrap(),
+    }
+}
+
+pub fn manganato() -> SelectorConnector {
+    SelectorConnector::new(
+        Connectors::Manganel,
+        info("manganel", "Manganato", "https://manganato.com"),
+        base(),
+    )
+}
+
+/// Selectors of the sites laid out like mangakakalot.
+fn kakalot() -> SelectorInfo {
+    SelectorInfo {
+        mangas_path: "/manga_list?type=latest&category=all&state=all&page={page}".to_string(),
+        search_path: Some("/search/story/{query}?page={page}".to_string()),
+
+        manga_title_filter: Regex::new(r"(?i)(\s+manga|\s+manhwa|\s+manhua)+\s*$").unwrap(),
+        query_manga_title: Selector::parse("div.manga-info-top ul.manga-info-text li h1").unwrap(),
+        query_manga_info: Some(
+            Selector::parse("div.manga-info-top ul.manga-info-text li").unwrap(),
+        ),
+        query_manga_description: Some(Selector::parse("div#noidungm").unwrap()),
+        query_mangas_page_count: Some(
+            Selector::parse("div.group_page a.page_last:last-of-type").unwrap(),
+        ),
+        query_mangas: Selector::parse("div.list-truyen-item-wrap h3 a").unwrap(),
+        query_search_mangas: Some(
+            Selector::parse("div.panel_story_list div.story_item > a").unwrap(),
+        ),
+        query_icon: Selector::parse("div.manga-info-pic img").unwrap(),
+        ..base()
+    }
+}
+
+pub fn mangakakalot() -> SelectorConnector {
+    SelectorConnector::new(
+        Connectors::Mangakakalot,
+        info("mangakakalot", "Mangakakalot", "https://mangakakalot.com"),
+        SelectorInfo {
+            domain: Regex::new(r"^(www\.|m\.)?mangakakalot\.(com|tv)$").unwrap(),
+            ..kakalot()
+        },
+    )
+}
+
+pub fn manganeloinfo() -> SelectorConnector {
+    SelectorConnector::new(
+        Connectors::Manganeloinfo,
+        info("manganeloinfo", "Manganelo.info", "https://manganelo.info"),
+        SelectorInfo {
+            domain: Regex::new(r"^(www\.|m\.)?manganelo\.info$").unwrap(),
+
+            mangas_path: "/manga-list/latest-manga?page={page}".to_string(),
+            search_path: Some("/search?s={query}&page={page}".to_string()),
+            ..kakalot()
+        },
+    )
+}
+
+pub fn mangabat() -> SelectorConnector {
+    SelectorConnector::new(
+        Connectors::Mangabat,
+        info("mangabat", "MangaBat", "https://h.mangabat.com"),
+        SelectorInfo {
+            domain: Regex::new(r"^(www\.|m\.|h\.|read\.)?mangabat\.com$").unwrap(),
+
+            mangas_path: "/manga-list-all/{page}".to_string(),
+            search_path: Some("/search/manga/{query}?page={page}".to_string()),
+
+            query_mangas: Selector::parse(
+                "div.panel-list-story div.list-story-item h3 a.item-title",
+            )
+            .unwrap(),
+            query_search_mangas: Some(
+                Selector::parse("div.panel-list-story div.list-story-item a.item-img").unwrap(),
+            ),
+            ..base()
+        },
+    )
+}
+
+pub fn mangairo() -> SelectorConnector {
+    SelectorConnector::new(
+        Connectors::Mangairo,
+        info("mangairo", "Mangairo", "https://w.mangairo.com"),
+        SelectorInfo {
+            domain: Regex::new(r"^(www\.|w\.|m\.|chap\.)?mangairo\.com$").unwrap(),
+
+            mangas_path: "/manga-list/type-latest/ctg-all/state-all/page-{page}".to_string(),
+            search_path: Some("/list/search/{query}?page={page}".to_string()),
+
+            query_manga_title: Selector::parse("div.story_content div.story_info_right h1")
+                .unwrap(),
+            query_manga_info: Some(
+                Selector::parse("div.story_content div.story_info_right li").unwrap(),
+            ),
+            query_manga_description: Some(Selector::parse("div#story_discription p").unwrap()),
+            query_mangas_page_count: Some(
+                Selector::parse("div.group-page a.page-last:last-of-type").unwrap(),
+            ),
+            query_mangas: Selector::parse("div.story-list div.story-item h3.story-name a").unwrap(),
+            query_search_mangas: Some(
+                Selector::parse("div.story-list div.story-item > a").unwrap(),
+            ),
+            query_icon: Selector::parse("div.story_info_left span.info_image img").unwrap(),
+            ..base()
+        },
+    )
+}
@@ -0,0 +1,655 @@
+use std::{env, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use druid::im::Vector;
+use reqwest::{header, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+
+use crate::core::{
+    connector::{FutureResult, StreamResult},
+    error::Error,
+    http::fetch_text,
+    Chapter, Connector, ConnectorInfo, Connectors, GlobalAPI, Manga, MangaDetails, MangaStatus,
+    Page,
+};
+
+const API_URL: &str = "https://api.mangadex.org";
+/// Overrides the api url, used to point the connector at a local stub server.
+const API_URL_VAR: &str = "MANGADEX_API_URL";
+const UPLOADS_URL: &str = "https://uploads.mangadex.org";
+const PAGE_SIZE: usize = 32;
+const FEED_SIZE: usize = 500;
+const CONTENT_RATINGS: [&str; 3] = ["safe", "suggestive", "erotica"];
+
+#[derive(Debug, Deserialize)]
+struct Collection<T> {
+    data: Vec<T>,
+    total: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entity<T> {
+    data: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct Relationship {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    attributes: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct MangaData {
+    id: String,
+    attributes: MangaAttributes,
+    #[serde(default)]
+    relationships: Vec<Relationship>,
+}
+
+/// Localized strings come as `{ "en": "..." }` maps, or `[]` when empty.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MangaAttributes {
+    title: Value,
+    #[serde(default)]
+    alt_titles: Vec<Value>,
+    #[serde(default)]
+    description: Value,
+    status: Option<String>,
+    #[serde(default)]
+    tags: Vec<TagData>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagData {
+    attributes: TagAttributes,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagAttributes {
+    name: Value,
+    group: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChapterData {
+    id: String,
+    attributes: ChapterAttributes,
+    #[serde(default)]
+    relationships: Vec<Relationship>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChapterAttributes {
+    volume: Option<String>,
+    chapter: Option<String>,
+    title: Option<String>,
+    publish_at: Option<DateTime<Utc>>,
+    external_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AtHome {
+    base_url: String,
+    chapter: AtHomeChapter,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtHomeChapter {
+    hash: String,
+    data: Vec<String>,
+}
+
+fn localized(value: &Value, languages: &[String]) -> Option<String> {
+    let map = value.as_object()?;
+    languages
+        .iter()
+        .map(String::as_str)
+        .chain(Some("en"))
+        .find_map(|language| map.get(language))
+        .or_else(|| map.values().next())
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// `url` without its trailing slash, to append paths to.
+fn base(url: &Url) -> &str {
+    url.as_str().trim_end_matches('/')
+}
+
+#[derive(Debug, Clone)]
+pub struct MangaDex {
+    info: ConnectorInfo,
+    api_url: Url,
+    /// Where covers are served from.
+    uploads_url: Url,
+    languages: Vec<String>,
+}
+
+impl MangaDex {
+    pub fn new(languages: Vec<String>) -> Self {
+        match env::var(API_URL_VAR)
+            .ok()
+            .and_then(|api_url| Url::parse(&api_url).ok())
+        {
+            Some(api_url) => Self::with_api_url(api_url, languages),
+            None => Self::with_urls(
+                Url::parse(API_URL).unwrap(),
+                Url::parse(UPLOADS_URL).unwrap(),
+                languages,
+            ),
+        }
+    }
+
+    /// A connector getting everything, covers included, from `api_url`.
+    pub fn with_api_url(api_url: Url, languages: Vec<String>) -> Self {
+        Self::with_urls(api_url.clone(), api_url, languages)
+    }
+
+    pub fn with_urls(api_url: Url, uploads_url: Url, languages: Vec<String>) -> Self {
+        Self {
+            info: ConnectorInfo {
+                id: "mangadex".to_string(),
+                label: "MangaDex".to_string(),
+                tags: vec![
+                    "manga".to_string(),
+                    "webtoon".to_string(),
+                    "multi-lingual".to_string(),
+                ],
+                url: Url::parse("https://mangadex.org").unwrap(),
+            },
+            api_url,
+            uploads_url,
+            languages,
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, Error> {
+        let mut uri = Url::parse(&format!("{}/{}", base(&self.api_url), path))
+            .map_err(|err| Error::RequestFail(err.to_string()))?;
+        uri.query_pairs_mut().extend_pairs(query);
+
+        let body = fetch_text(
+            GlobalAPI::global()
+                .http
+                .get(uri)
+                .header(header::ACCEPT, "application/json"),
+        )
+        .await?;
+        serde_json::from_str(&body).map_err(|err| Error::RequestFail(err.to_string()))
+    }
+
+    /// Takes the uuid following `segment` in urls like `https://mangadex.org/title/{id}/slug`.
+    fn id_from_url(url: &str, segment: &str) -> Result<String, Error> {
+        Url::parse(url)
+            .ok()
+            .and_then(|url| {
+                let mut segments = url.path_segments()?;
+                segments.find(|part| *part == segment)?;
+                segments.next().map(str::to_string)
+            })
+            .ok_or_else(|| Error::RequestFail(format!("Not a MangaDex {} url: {}", segment, url)))
+    }
+
+    fn listing_query(&self, page: i32) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("limit", PAGE_SIZE.to_string()),
+            (
+                "offset",
+                (page.max(1) as usize - 1)
+                    .saturating_mul(PAGE_SIZE)
+                    .to_string(),
+            ),
+            ("includes[]", "cover_art".to_string()),
+        ];
+        query.extend(
+            CONTENT_RATINGS
+                .iter()
+                .map(|rating| ("contentRating[]", rating.to_string())),
+        );
+        query.extend(
+            self.languages
+                .iter()
+                .map(|language| ("availableTranslatedLanguage[]", language.clone())),
+        );
+        query
+    }
+
+    fn cover_url(&self, manga: &MangaData) -> Option<String> {
+        manga
+            .relationships
+            .iter()
+            .find(|relationship| relationship.kind == "cover_art")
+            .and_then(|relationship| relationship.attributes.get("fileName"))
+            .and_then(Value::as_str)
+            .map(|file_name| {
+                format!(
+                    "{}/covers/{}/{}.256.jpg",
+                    base(&self.uploads_url),
+                    manga.id,
+                    file_name
+                )
+            })
+    }
+
+    fn to_manga(&self, manga: MangaData, with_details: bool) -> Manga {
+        let details = if with_details {
+            let names = |kind: &str| -> Vector<Arc<str>> {
+                manga
+                    .relationships
+                    .iter()
+                    .filter(|relationship| relationship.kind == kind)
+                    .filter_map(|relationship| relationship.attributes.get("name"))
+                    .filter_map(Value::as_str)
+                    .map(Arc::from)
+                    .collect()
+            };
+            let mut authors = names("author");
+            for artist in names("artist") {
+                if !authors.contains(&artist) {
+                    authors.push_back(artist);
+                }
+            }
+            Some(MangaDetails {
+                alt_titles: manga
+                    .attributes
+                    .alt_titles
+                    .iter()
+                    .filter_map(|title| localized(title, &self.languages))
+                    .map(Arc::from)
+                    .collect(),
+                authors,
+                genres: manga
+                    .attributes
+                    .tags
+                    .iter()
+                    .filter(|tag| {
+                        tag.attributes.group == "genre" || tag.attributes.group == "theme"
+                    })
+                    .filter_map(|tag| localized(&tag.attributes.name, &self.languages))
+                    .map(Arc::from)
+                    .collect(),
+                status: manga
+                    .attributes
+                    .status
+                    .as_deref()
+                    .map(MangaStatus::parse)
+                    .unwrap_or_default(),
+                last_updated: manga
+                    .attributes
+                    .updated_at
+                    .map(|date| date.format("%b %d, %Y").to_string().into()),
+                description: localized(&manga.attributes.description, &self.languages)
+                    .map(Arc::from),
+            })
+        } else {
+            None
+        };
+
+        Manga {
+            url: format!("{}title/{}", self.info.url, manga.id).into(),
+            title: localized(&manga.attributes.title, &self.languages)
+                .unwrap_or_default()
+                .into(),
+            icon_url: self.cover_url(&manga).unwrap_or_default().into(),
+            connector: Connectors::MangaDex,
+            details,
+        }
+    }
+
+    fn to_chapter(&self, chapter: ChapterData, manga: &Manga) -> Chapter {
+        let attributes = chapter.attributes;
+        let mut title = attributes
+            .chapter
+            .as_ref()
+            .map(|number| format!("Chapter {}", number))
+            .unwrap_or_else(|| "Oneshot".to_string());
+        if let Some(name) = attributes.title.as_deref().filter(|name| !name.is_empty()) {
+            title = format!("{}: {}", title, name);
+        }
+
+        Chapter {
+            url: format!("{}chapter/{}", self.info.url, chapter.id).into(),
+            title: title.into(),
+            connector: Connectors::MangaDex,
+            manga: manga.clone(),
+            number: attributes
+                .chapter
+                .as_deref()
+                .and_then(|number| number.parse().ok()),
+            volume: attributes.volume.map(Arc::from),
+            uploaded: attributes.publish_at,
+            scanlator: chapter
+                .relationships
+                .iter()
+                .find(|relationship| relationship.kind == "scanlation_group")
+                .and_then(|relationship| relationship.attributes.get("name"))
+                .and_then(Value::as_str)
+                .map(Arc::from),
+            views: None,
+        }
+    }
+}
+
+impl Connector for MangaDex {
+    fn get_connector_info(&self) -> ConnectorInfo {
+        self.info.clone()
+    }
+
+    fn can_handle_uri(&self, uri: Url) -> bool {
+        matches!(
+            uri.domain(),
+            Some("mangadex.org") | Some("www.mangadex.org")
+        )
+    }
+
+    fn get_manga_from_url(&self, manga_url: Url) -> FutureResult<'_, Manga> {
+        Box::pin(async move {
+            let id = Self::id_from_url(manga_url.as_str(), "title")?;
+            let manga: Entity<MangaData> = self
+                .get_json(
+                    &format!("manga/{}", id),
+                    &[
+                        ("includes[]", "cover_art".to_string()),
+                        ("includes[]", "author".to_string()),
+                        ("includes[]", "artist".to_string()),
+                    ],
+                )
+                .await?;
+            Ok(self.to_manga(manga.data, true))
+        })
+    }
+
+    fn get_mangas(&self) -> StreamResult<'_, Manga> {
+        Box::pin(async_stream::try_stream! {
+            let mut page = 1;
+            loop {
+                let mut query = self.listing_query(page);
+                query.push(("order[followedCount]", "desc".to_string()));
+                let mangas: Collection<MangaData> = self.get_json("manga", &query).await?;
+                let done = mangas.data.is_empty() || page as usize * PAGE_SIZE >= mangas.total;
+                for manga in mangas.data {
+                    yield self.to_manga(manga, false);
+                }
+                if done {
+                    break;
+                }
+                page += 1;
+            }
+        })
+    }
+
+    fn get_manga_icon(&self, manga_url: Url) -> FutureResult<'_, Url> {
+        Box::pin(async move {
+            let id = Self::id_from_url(manga_url.as_str(), "title")?;
+            let manga: Entity<MangaData> = self
+                .get_json(
+                    &format!("manga/{}", id),
+                    &[("includes[]", "cover_art".to_string())],
+                )
+                .await?;
+            self.cover_url(&manga.data)
+                .and_then(|cover| Url::parse(&cover).ok())
+                .ok_or_else(|| Error::RequestFail(format!("No cover found for {}", manga_url)))
+        })
+    }
+
+    fn get_mangas_from_page(&self, page: i32) -> StreamResult<'_, Manga> {
+        Box::pin(async_stream::try_stream! {
+            let mut query = self.listing_query(page);
+            query.push(("order[followedCount]", "desc".to_string()));
+            let mangas: Collection<MangaData> = self.get_json("manga", &query).await?;
+            for manga in mangas.data {
+                yield self.to_manga(manga, false);
+            }
+        })
+    }
+
+    fn search(&self, query: String, page: i32) -> StreamResult<'_, Manga> {
+        Box::pin(async_stream::try_stream! {
+            let title = query.trim().to_string();
+            if !title.is_empty() {
+                let mut query = self.listing_query(page);
+                query.push(("title", title));
+                query.push(("order[relevance]", "desc".to_string()));
+                let mangas: Collection<MangaData> = self.get_json("manga", &query).await?;
+                for manga in mangas.data {
+                    yield self.to_manga(manga, false);
+                }
+            }
+        })
+    }
+
+    fn get_chapters(&self, manga: Manga) -> StreamResult<'_, Chapter> {
+        Box::pin(async_stream::try_stream! {
+            let id = Self::id_from_url(&manga.url, "title")?;
+            let mut offset = 0;
+            loop {
+                let mut query = vec![
+                    ("limit", FEED_SIZE.to_string()),
+                    ("offset", offset.to_string()),
+                    ("order[volume]", "desc".to_string()),
+                    ("order[chapter]", "desc".to_string()),
+                    ("includes[]", "scanlation_group".to_string()),
+                ];
+                query.extend(CONTENT_RATINGS.iter().map(|rating| ("contentRating[]", rating.to_string())));
+                query.extend(
+                    self.languages
+                        .iter()
+                        .map(|language| ("translatedLanguage[]", language.clone())),
+                );
+                let chapters: Collection<ChapterData> = self
+                    .get_json(&format!("manga/{}/feed", id), &query)
+                    .await?;
+
+                offset += chapters.data.len();
+                let done = chapters.data.is_empty() || offset >= chapters.total;
+                // Chapters hosted on other websites have no pages on MangaDex.
+                let chapters = chapters
+                    .data
+                    .into_iter()
+                    .filter(|chapter| chapter.attributes.external_url.is_none());
+                for chapter in chapters {
+                    yield self.to_chapter(chapter, &manga);
+                }
+                if done {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn get_pages(&self, chapter: Chapter) -> StreamResult<'_, Page> {
+        Box::pin(async_stream::try_stream! {
+            let id = Self::id_from_url(&chapter.url, "chapter")?;
+            let at_home: AtHome = self
+                .get_json(&format!("at-home/server/{}", id), &[])
+                .await?;
+
+            for file in at_home.chapter.data {
+                yield Page {
+                    url: format!("{}/data/{}/{}", at_home.base_url, at_home.chapter.hash, file).into(),
+                    referer: chapter.url.clone(),
+                    connector: Connectors::MangaDex,
+                }
+            }
+        })
+    }
+}
+
+/// The stub server of the example, serving the fixtures to the tests.
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../../../examples/mangadex_stub.rs"]
+mod stub;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Once;
+
+    use futures::{StreamExt, TryStreamExt};
+    use tokio::net::TcpListener;
+
+    use crate::core::http::HttpConfig;
+
+    use super::*;
+
+    const ONE_PIECE: &str = "https://mangadex.org/title/a1c7c817-4e59-43b7-9365-09675a149a6f";
+
+    static INSTALL: Once = Once::new();
+
+    /// A connector pointed at the stub, serving the fixtures on a free port.
+    async fn connector() -> MangaDex {
+        INSTALL.call_once(|| {
+            GlobalAPI::install(
+                None,
+                None,
+                None,
+                vec!["en".to_string()],
+                HttpConfig::default(),
+            )
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(stub::serve(listener, stub::fixtures_dir()));
+        MangaDex::with_api_url(api_url, vec!["en".to_string()])
+    }
+
+    #[tokio::test]
+    async fn search() {
+        let connector = connector().await;
+        let mangas: Vec<Manga> = connector
+            .search("one piece".to_string(), 1)
+            .try_collect()
+            .await
+            .unwrap();
+
+        let titles: Vec<&str> = mangas.iter().map(|manga| manga.title.as_ref()).collect();
+        assert_eq!(titles, ["One Piece", "Solo Leveling"]);
+        assert_eq!(mangas[0].url.as_ref(), ONE_PIECE);
+        assert_eq!(
+            mangas[0].icon_url.as_ref(),
+            format!(
+                "{}/covers/a1c7c817-4e59-43b7-9365-09675a149a6f/6ad1a4a6-96ad-4e5c-8fe4-29b5d5c5cb7a.jpg.256.jpg",
+                base(&connector.uploads_url)
+            )
+        );
+        assert!(mangas.iter().all(|manga| manga.details.is_none()));
+    }
+
+    #[tokio::test]
+    async fn blank_search_asks_nothing() {
+        let connector = connector().await;
+        let mangas = connector
+            .search("  ".to_string(), 1)
+            .collect::<Vec<_>>()
+            .await;
+        assert!(mangas.is_empty());
+    }
+
+    #[tokio::test]
+    async fn manga() {
+        let connector = connector().await;
+        let manga = connector
+            .get_manga_from_url(Url::parse(ONE_PIECE).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(manga.title.as_ref(), "One Piece");
+        assert!(manga.icon_url.starts_with(base(&connector.uploads_url)));
+        let details = manga.details.unwrap();
+        // Authors who are also the artist are listed once.
+        assert_eq!(
+            details.authors,
+            Vector::from(vec![Arc::from("Oda Eiichiro")])
+        );
+        // Format tags are not genres.
+        assert_eq!(
+            details.genres,
+            ["Action", "Adventure", "Comedy"]
+                .iter()
+                .map(|genre| Arc::from(*genre))
+                .collect::<Vector<Arc<str>>>()
+        );
+        assert_eq!(details.status, MangaStatus::Ongoing);
+        assert_eq!(details.alt_titles.len(), 3);
+        assert!(details
+            .description
+            .unwrap()
+            .starts_with("Gol D. Roger was known as the Pirate King"));
+        assert_eq!(details.last_updated.as_deref(), Some("Sep 25, 2021"));
+    }
+
+    #[tokio::test]
+    async fn chapters() {
+        let connector = connector().await;
+        let manga = connector
+            .get_manga_from_url(Url::parse(ONE_PIECE).unwrap())
+            .await
+            .unwrap();
+        let chapters: Vec<Chapter> = connector.get_chapters(manga).try_collect().await.unwrap();
+
+        // Chapter 1023 is hosted elsewhere.
+        let titles: Vec<&str> = chapters
+            .iter()
+            .map(|chapter| chapter.title.as_ref())
+            .collect();
+        assert_eq!(titles, ["Chapter 1024: Somebody", "Chapter 1023.5"]);
+        assert_eq!(
+            chapters[0].url.as_ref(),
+            "https://mangadex.org/chapter/d5a8a4c4-8f3b-4a3c-8d6a-6c1c4e1e9f01"
+        );
+        assert_eq!(chapters[0].number, Some(1024.));
+        assert_eq!(chapters[1].number, Some(1023.5));
+        assert_eq!(chapters[0].volume.as_deref(), Some("100"));
+        assert_eq!(chapters[0].scanlator.as_deref(), Some("TCB Scans"));
+        assert_eq!(chapters[1].scanlator, None);
+        assert_eq!(
+            chapters[0]
+                .uploaded
+                .map(|date| date.to_rfc3339())
+                .as_deref(),
+            Some("2021-09-12T15:00:00+00:00")
+        );
+    }
+
+    #[tokio::test]
+    async fn pages() {
+        let connector = connector().await;
+        let manga = connector
+            .get_manga_from_url(Url::parse(ONE_PIECE).unwrap())
+            .await
+            .unwrap();
+        let chapter = connector
+            .get_chapters(manga)
+            .try_next()
+            .await
+            .unwrap()
+            .unwrap();
+        let pages: Vec<Page> = connector
+            .get_pages(chapter.clone())
+            .try_collect()
+            .await
+            .unwrap();
+
+        let urls: Vec<&str> = pages.iter().map(|page| page.url.as_ref()).collect();
+        assert_eq!(
+            urls,
+            [
+                "http://127.0.0.1:8000/data/3c2e1f8a9b7d6c5e4f3a2b1c0d9e8f7a/1-b5ae4c2e0f6a1e1f9c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b.png",
+                "http://127.0.0.1:8000/data/3c2e1f8a9b7d6c5e4f3a2b1c0d9e8f7a/2-c6bf5d3f1a7b2f2a0d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c.png",
+            ]
+        );
+        assert!(pages.iter().all(|page| page.referer == chapter.url));
+    }
+}
@@ -1 +1,4 @@
-pub mod manganel;
+pub mod local;
+pub mod mangabox;
+pub mod mangadex;
+pub mod selector;
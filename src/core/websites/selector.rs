@@ -0,0 +1,705 @@
+use std::{fs, iter, path::Path, sync::Arc};
+
+use cssparser::ToCss;
+
+use druid::im::Vector;
+use futures::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Url;
+use scraper::{node::Element, ElementRef, Html, Selector};
+use serde::Deserialize;
+
+use crate::core::{
+    connector::{FutureResult, StreamResult, COVER_CONCURRENCY},
+    error::Error,
+    http::fetch_text,
+    Chapter, Connector, ConnectorInfo, Connectors, GlobalAPI, Manga, MangaDetails, MangaStatus,
+    Page,
+};
+
+/// How many ancestors of a listing link are searched for its cover, the search
+/// also stops at the first holding other listing links.
+const LISTING_ITEM_DEPTH: usize = 4;
+
+static NOT_ALPHANUMERIC: Lazy<Regex> = Lazy::new(|| Regex::new(r"[^a-z0-9]+").unwrap());
+/// Separates authors, genres and the like in a details row.
+static LIST_SEPARATOR: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s*[;,]\s*|\s+-\s+").unwrap());
+/// Alternative titles may contain commas and dashes.
+static TITLE_SEPARATOR: Lazy<Regex> = Lazy::new(|| Regex::new(r";").unwrap());
+/// The last page number in a pagination link or label.
+static TRAILING_NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+)\D*$").unwrap());
+
+/// Attributes holding the image url, lazy loaded sites keep the real one in `data-src`.
+const IMAGE_ATTRIBUTES: [&str; 3] = ["data-src", "data-lazy-src", "src"];
+
+fn image_source(element: &Element) -> Option<&str> {
+    IMAGE_ATTRIBUTES
+        .iter()
+        .find_map(|attribute| element.attr(attribute))
+        .map(str::trim)
+        .filter(|src| !src.is_empty())
+}
+
+/// Turns a parsed selector back into css, for error messages.
+fn css(selector: &Selector) -> String {
+    selector
+        .selectors
+        .iter()
+        .map(|selector| selector.to_css_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Everything a [`SelectorConnector`] needs to find its way around a website.
+#[derive(Debug, Clone)]
+pub struct SelectorInfo {
+    pub domain: Regex,
+
+    pub mangas_path: String,
+    pub search_path: Option<String>,
+    pub search_separator: String,
+
+    pub manga_title_filter: Regex,
+    pub chapter_title_filter: Regex,
+    pub manga_description_filter: Regex,
+    pub query_manga_title: Selector,
+    pub query_manga_info: Option<Selector>,
+    pub query_manga_description: Option<Selector>,
+    pub query_mangas_page_count: Option<Selector>,
+    pub query_mangas: Selector,
+    pub query_mangas_icon: Selector,
+    pub query_search_mangas: Option<Selector>,
+
+    pub query_icon: Selector,
+
+    pub query_chapters: Selector,
+    pub query_chapter_link: Selector,
+    pub query_chapter_views: Option<Selector>,
+    pub query_chapter_date: Option<Selector>,
+    pub chapter_date_formats: Vec<String>,
+
+    pub query_pages: Selector,
+}
+
+/// A connector that scrapes a website using only the selectors and filters
+/// found in its [`SelectorInfo`].
+#[derive(Debug, Clone)]
+pub struct SelectorConnector {
+    info: ConnectorInfo,
+    selectors: SelectorInfo,
+    connector: Connectors,
+}
+
+impl SelectorConnector {
+    pub fn new(connector: Connectors, info: ConnectorInfo, selectors: SelectorInfo) -> Self {
+        Self {
+            info,
+            selectors,
+            connector,
+        }
+    }
+
+    pub fn connector(&self) -> Connectors {
+        self.connector.clone()
+    }
+
+    /// Loads every `*.toml` and `*.json` connector definition found in `dir`.
+    pub fn load_dir(dir: &Path) -> Vec<Self> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter_map(|path| {
+                let definition = fs::read_to_string(&path)
+                    .map_err(|err| Error::IoError(err.to_string()))
+                    .and_then(|content| {
+                        match path.extension().and_then(|extension| extension.to_str()) {
+                            Some("toml") => toml::from_str::<ConnectorDefinition>(&content)
+                                .map_err(|err| Error::InvalidConnector(err.to_string())),
+                            Some("json") => serde_json::from_str::<ConnectorDefinition>(&content)
+                                .map_err(|err| Error::InvalidConnector(err.to_string())),
+                            _ => Err(Error::InvalidConnector("Unknown format".to_string())),
+                        }
+                    });
+                match definition.and_then(Self::from_definition) {
+                    Ok(connector) => {
+                        log::info!("loaded connector definition: {:?}", &path);
+                        Some(connector)
+                    }
+                    Err(err) => {
+                        log::error!("failed to load connector {:?}: {}", &path, err);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    pub fn from_definition(definition: ConnectorDefinition) -> Result<Self, Error> {
+        let (info, selectors) = definition.into_parts()?;
+        Ok(Self::new(
+            Connectors::Custom(info.id.as_str().into()),
+            info,
+            selectors,
+        ))
+    }
+
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        self.info
+            .url
+            .join(path)
+            .map_err(|err| Error::RequestFail(format!("{}: {}", path, err)))
+    }
+
+    fn parse_error(&self, selector: &Selector, url: &Url) -> Error {
+        Error::NoMatch {
+            connector: self.info.label.clone(),
+            selector: css(selector),
+            url: url.to_string(),
+        }
+    }
+
+    fn parse_icon(&self, dom: &Html, manga_url: &Url) -> Result<Url, Error> {
+        dom.select(&self.selectors.query_icon)
+            .last()
+            .and_then(|element_ref| image_source(element_ref.value()))
+            .and_then(|src| self.info.url.join(src).ok())
+            .ok_or_else(|| self.parse_error(&self.selectors.query_icon, manga_url))
+    }
+
+    /// Builds a manga from a listing link, taking the cover from the link itself
+    /// or from the closest listing item around it.
+    fn listing_manga(
+        &self,
+        element_ref: ElementRef,
+        query: &Selector,
+        title: &str,
+    ) -> Option<Manga> {
+        let manga_url = self.info.url.join(element_ref.value().attr("href")?).ok()?;
+        let title = self
+            .selectors
+            .manga_title_filter
+            .replace(title, "")
+            .trim()
+            .to_string();
+        let icon_url = iter::successors(Some(element_ref), |element_ref| {
+            element_ref.parent().and_then(ElementRef::wrap)
+        })
+        .take(LISTING_ITEM_DEPTH)
+        .take_while(|ancestor| ancestor.select(query).nth(1).is_none())
+        .find_map(|element_ref| {
+            element_ref
+                .select(&self.selectors.query_mangas_icon)
+                .find_map(|icon| image_source(icon.value()))
+        })
+        .and_then(|src| self.info.url.join(src).ok())
+        .map(|src| src.to_string())
+        .unwrap_or_default();
+
+        Some(Manga {
+            title: title.into(),
+            url: manga_url.as_str().into(),
+            icon_url: icon_url.into(),
+            connector: self.connector(),
+            details: None,
+        })
+    }
+
+    fn keyword(&self, query: &str) -> String {
+        NOT_ALPHANUMERIC
+            .replace_all(
+                &query.trim().to_lowercase(),
+                self.selectors.search_separator.as_str(),
+            )
+            .trim_matches(|c| self.selectors.search_separator.contains(c))
+            .to_string()
+    }
+
+    fn parse_manga_details(&self, dom: &Html) -> MangaDetails {
+        let link = Selector::parse("a").unwrap();
+        let split = |value: &str, separator: &Regex| -> Vector<Arc<str>> {
+            separator
+                .split(value.trim())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(Arc::from)
+                .collect()
+        };
+
+        let mut details = MangaDetails::default();
+        let rows = self
+            .selectors
+            .query_manga_info
+            .iter()
+            .flat_map(|query| dom.select(query));
+        for row in rows {
+            let text = row.text().collect::<String>();
+            let (label, value) = match text.split_once(':') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let label = label.trim().to_lowercase();
+            let links: Vector<Arc<str>> = row
+                .select(&link)
+                .map(|element_ref| element_ref.text().collect::<String>().trim().to_string())
+                .filter(|value| !value.is_empty())
+                .map(Arc::from)
+                .collect();
+            let values = if links.is_empty() {
+                split(value, &LIST_SEPARATOR)
+            } else {
+                links
+            };
+
+            if label.starts_with("alternative") {
+                details.alt_titles = split(value, &TITLE_SEPARATOR);
+            } else if label.starts_with("author") {
+                details.authors = values;
+            } else if label.starts_with("genre") {
+                details.genres = values;
+            } else if label.starts_with("status") {
+                details.status = MangaStatus::parse(value);
+            } else if label.contains("updated") {
+                details.last_updated = Some(value.trim().into());
+            }
+        }
+
+        details.description = self
+            .selectors
+            .query_manga_description
+            .as_ref()
+            .and_then(|query| dom.select(query).next())
+            .map(|element_ref| {
+                self.selectors
+                    .manga_description_filter
+                    .replace(element_ref.text().collect::<String>().trim(), "")
+                    .trim()
+                    .into()
+            })
+            .filter(|description: &Arc<str>| !description.is_empty());
+
+        details
+    }
+}
+
+impl Connector for SelectorConnector {
+    fn get_connector_info(&self) -> ConnectorInfo {
+        self.info.clone()
+    }
+
+    fn can_handle_uri(&self, uri: Url) -> bool {
+        if let Some(domain) = uri.domain() {
+            self.selectors.domain.is_match(domain)
+        } else {
+            false
+        }
+    }
+
+    fn get_manga_from_url(&self, manga_url: Url) -> FutureResult<'_, Manga> {
+        Box::pin(async move {
+            let body = fetch_text(GlobalAPI::global().http.get(manga_url.clone())).await?;
+            let (title, icon_url, details) = {
+                let dom = Html::parse_document(&body);
+                let title = dom
+                    .select(&self.selectors.query_manga_title)
+                    .last()
+                    .map(|element_ref| element_ref.text().collect::<String>())
+                    .ok_or_else(|| {
+                        self.parse_error(&self.selectors.query_manga_title, &manga_url)
+                    })?;
+                (
+                    title,
+                    self.parse_icon(&dom, &manga_url)?,
+                    self.parse_manga_details(&dom),
+                )
+            };
+
+            Ok(Manga {
+                title: title.trim().into(),
+                url: manga_url.as_str().into(),
+                icon_url: icon_url.as_str().into(),
+                connector: self.connector(),
+                details: Some(details),
+            })
+        })
+    }
+
+    fn get_mangas(&self) -> StreamResult<'_, Manga> {
+        Box::pin(async_stream::try_stream! {
+            let uri = self.url(&self.selectors.mangas_path.replace("{page}", "1"))?;
+            let body = fetch_text(GlobalAPI::global().http.get(uri)).await?;
+
+            let page_count = {
+                let dom = Html::parse_document(&body);
+                self.selectors
+                    .query_mangas_page_count
+                    .as_ref()
+                    .and_then(|query| dom.select(query).last())
+                    .and_then(|element_ref| {
+                        let text = element_ref
+                            .value()
+                            .attr("href")
+                            .map(str::to_string)
+                            .unwrap_or_else(|| element_ref.text().collect());
+                        TRAILING_NUMBER
+                            .captures(&text)
+                            .and_then(|captures| captures[1].parse::<i32>().ok())
+                    })
+                    .unwrap_or(1)
+            };
+
+            for page in 1..=page_count {
+                let mut stream = self.get_mangas_from_page(page);
+                while let Some(result) = stream.next().await {
+                    let manga = result?;
+                    yield manga;
+                }
+            };
+        })
+    }
+
+    fn get_manga_icon(&self, manga_url: Url) -> FutureResult<'_, Url> {
+        Box::pin(async move {
+            let body = fetch_text(GlobalAPI::global().http.get(manga_url.clone())).await?;
+            let dom = Html::parse_document(&body);
+            self.parse_icon(&dom, &manga_url)
+        })
+    }
+
+    fn get_mangas_from_page(&self, page: i32) -> StreamResult<'_, Manga> {
+        Box::pin(async_stream::try_stream! {
+            let uri = self.url(&self.selectors.mangas_path.replace("{page}", &page.to_string()))?;
+            let body = fetch_text(GlobalAPI::global().http.get(uri.clone())).await?;
+            let mangas: Vec<Manga> = {
+                let dom = Html::parse_document(&body);
+
+                dom.select(&self.selectors.query_mangas)
+                    .filter_map(|element_ref| {
+                        let title = element_ref.text().collect::<String>();
+                        self.listing_manga(element_ref, &self.selectors.query_mangas, &title)
+                    })
+                    .collect()
+            };
+            if mangas.is_empty() {
+                Err(self.parse_error(&self.selectors.query_mangas, &uri))?;
+            }
+
+            let mut mangas = stream::iter(mangas)
+                .map(|manga| self.resolve_cover(manga))
+                .buffer_unordered(COVER_CONCURRENCY);
+            while let Some(manga) = mangas.next().await {
+                yield manga;
+            }
+        })
+    }
+
+    fn search(&self, query: String, page: i32) -> StreamResult<'_, Manga> {
+        Box::pin(async_stream::try_stream! {
+            let keyword = self.keyword(&query);
+
+            let mangas: Vec<Manga> = match (&self.selectors.search_path, &self.selectors.query_search_mangas) {
+                (Some(search_path), Some(query_search_mangas)) if !keyword.is_empty() => {
+                    let uri = self.url(
+                        &search_path
+                            .replace("{query}", &keyword)
+                            .replace("{page}", &page.to_string()),
+                    )?;
+                    let body = fetch_text(GlobalAPI::global().http.get(uri)).await?;
+                    let dom = Html::parse_document(&body);
+
+                    dom.select(query_search_mangas)
+                        .filter_map(|element_ref| {
+                            let title = element_ref
+                                .value()
+                                .attr("title")
+                                .map(str::to_string)
+                                .unwrap_or_else(|| element_ref.text().collect());
+                            self.listing_manga(element_ref, query_search_mangas, &title)
+                        })
+                        .collect()
+                }
+                _ => Vec::new(),
+            };
+
+            let mut mangas = stream::iter(mangas)
+                .map(|manga| self.resolve_cover(manga))
+                .buffer_unordered(COVER_CONCURRENCY);
+            while let Some(manga) = mangas.next().await {
+                yield manga;
+            }
+        })
+    }
+
+    fn get_chapters(&self, manga: Manga) -> StreamResult<'_, Chapter> {
+        Box::pin(async_stream::try_stream! {
+            let uri = Url::parse(&manga.url).map_err(|err| Error::RequestFail(err.to_string()))?;
+            let body = fetch_text(GlobalAPI::global().http.get(uri)).await?;
+            let chapters: Vec<Chapter> = {
+                let dom = Html::parse_document(&body);
+                let date_formats: Vec<&str> = self
+                    .selectors
+                    .chapter_date_formats
+                    .iter()
+                    .map(String::as_str)
+                    .collect();
+                dom.select(&self.selectors.query_chapters)
+                    .filter_map(|row| {
+                        let link = row.select(&self.selectors.query_chapter_link).next()?;
+                        let url = self.info.url.join(link.value().attr("href")?).ok()?;
+                        let text = link.text().collect::<String>();
+                        let title = self
+                            .selectors
+                            .chapter_title_filter
+                            .replace(&text.replace(manga.title.as_ref(), ""), "")
+                            .trim()
+                            .to_string();
+                        let views = self
+                            .selectors
+                            .query_chapter_views
+                            .as_ref()
+                            .and_then(|query| row.select(query).next())
+                            .and_then(|views| Chapter::parse_views(&views.text().collect::<String>()));
+                        let uploaded = self
+                            .selectors
+                            .query_chapter_date
+                            .as_ref()
+                            .and_then(|query| row.select(query).next())
+                            .and_then(|date| {
+                                let date = date
+                                    .value()
+                                    .attr("title")
+                                    .map(str::to_string)
+                                    .unwrap_or_else(|| date.text().collect());
+                                Chapter::parse_date(&date, &date_formats)
+                            });
+                        Some(Chapter {
+                            number: Chapter::parse_number(&text, url.as_str()),
+                            volume: Chapter::parse_volume(&text),
+                            url: url.as_str().into(),
+                            title: title.into(),
+                            connector: self.connector(),
+                            manga: manga.clone(),
+                            uploaded,
+                            scanlator: None,
+                            views,
+                        })
+                    })
+                    .collect()
+            };
+
+            for chapter in chapters {
+                yield chapter
+            }
+        })
+    }
+
+    fn get_pages(&self, chapter: Chapter) -> StreamResult<'_, Page> {
+        Box::pin(async_stream::try_stream! {
+            let uri = Url::parse(&chapter.url).map_err(|err| Error::RequestFail(err.to_string()))?;
+            let body = fetch_text(GlobalAPI::global().http.get(uri.clone())).await?;
+            let info: Vec<Arc<str>> = {
+                let dom = Html::parse_document(&body);
+                dom.select(&self.selectors.query_pages)
+                    .filter_map(|element_ref| image_source(element_ref.value()))
+                    .filter_map(|src| self.info.url.join(src).ok())
+                    .map(|src| src.as_str().into())
+                    .collect()
+            };
+            if info.is_empty() {
+                Err(self.parse_error(&self.selectors.query_pages, &uri))?;
+            }
+
+            for page_url in info {
+                yield Page {
+                    url: page_url,
+                    referer: chapter.url.clone(),
+                    connector: self.connector(),
+                }
+            }
+        })
+    }
+}
+
+/// A connector definition as written by users in the `connectors` config
+/// folder, either as TOML or JSON.
+///
+/// ```toml
+/// id = "mymirror"
+/// label = "My Mirror"
+/// tags = ["manga", "english"]
+/// url = "https://mymirror.com"
+/// domain = '^(www\.)?mymirror\.com$'
+///
+/// [paths]
+/// mangas = "/manga-list/{page}"
+/// search = "/search/{query}?page={page}"
+/// search_separator = "_"
+///
+/// [selectors]
+/// manga_title = "div.story-info-right h1"
+/// mangas = "div.list-truyen-item-wrap h3 a"
+/// icon = "div.manga-info-pic img"
+/// chapters = "div.chapter-list div.row"
+/// pages = "div.container-chapter-reader img"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnectorDefinition {
+    pub id: String,
+    pub label: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub url: String,
+    pub domain: String,
+    pub paths: PathsDefinition,
+    #[serde(default)]
+    pub filters: FiltersDefinition,
+    pub selectors: SelectorsDefinition,
+    #[serde(default)]
+    pub chapter_date_formats: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PathsDefinition {
+    pub mangas: String,
+    pub search: Option<String>,
+    #[serde(default = "PathsDefinition::default_search_separator")]
+    pub search_separator: String,
+}
+
+impl PathsDefinition {
+    fn default_search_separator() -> String {
+        "+".to_string()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FiltersDefinition {
+    pub manga_title: String,
+    pub chapter_title: String,
+    pub manga_description: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelectorsDefinition {
+    pub manga_title: String,
+    pub manga_info: Option<String>,
+    pub manga_description: Option<String>,
+    pub mangas_page_count: Option<String>,
+    pub mangas: String,
+    #[serde(default = "SelectorsDefinition::default_image")]
+    pub mangas_icon: String,
+    pub search_mangas: Option<String>,
+    pub icon: String,
+    pub chapters: String,
+    #[serde(default = "SelectorsDefinition::default_link")]
+    pub chapter_link: String,
+    pub chapter_views: Option<String>,
+    pub chapter_date: Option<String>,
+    pub pages: String,
+}
+
+impl SelectorsDefinition {
+    fn default_image() -> String {
+        "img".to_string()
+    }
+
+    fn default_link() -> String {
+        "a".to_string()
+    }
+}
+
+fn parse_selector(selector: &str) -> Result<Selector, Error> {
+    Selector::parse(selector)
+        .map_err(|err| Error::InvalidConnector(format!("{}: {:?}", selector, err)))
+}
+
+fn parse_optional_selector(selector: &Option<String>) -> Result<Option<Selector>, Error> {
+    selector.as_deref().map(parse_selector).transpose()
+}
+
+fn parse_regex(regex: &str) -> Result<Regex, Error> {
+    Regex::new(regex).map_err(|err| Error::InvalidConnector(err.to_string()))
+}
+
+impl ConnectorDefinition {
+    pub fn into_parts(self) -> Result<(ConnectorInfo, SelectorInfo), Error> {
+        let selectors = &self.selectors;
+        let selector_info = SelectorInfo {
+            domain: parse_regex(&self.domain)?,
+
+            mangas_path: self.paths.mangas.clone(),
+            search_path: self.paths.search.clone(),
+            search_separator: self.paths.search_separator.clone(),
+
+            manga_title_filter: parse_regex(&self.filters.manga_title)?,
+            chapter_title_filter: parse_regex(&self.filters.chapter_title)?,
+            manga_description_filter: parse_regex(&self.filters.manga_description)?,
+            query_manga_title: parse_selector(&selectors.manga_title)?,
+            query_manga_info: parse_optional_selector(&selectors.manga_info)?,
+            query_manga_description: parse_optional_selector(&selectors.manga_description)?,
+            query_mangas_page_count: parse_optional_selector(&selectors.mangas_page_count)?,
+            query_mangas: parse_selector(&selectors.mangas)?,
+            query_mangas_icon: parse_selector(&selectors.mangas_icon)?,
+            query_search_mangas: parse_optional_selector(&selectors.search_mangas)?,
+
+            query_icon: parse_selector(&selectors.icon)?,
+
+            query_chapters: parse_selector(&selectors.chapters)?,
+            query_chapter_link: parse_selector(&selectors.chapter_link)?,
+            query_chapter_views: parse_optional_selector(&selectors.chapter_views)?,
+            query_chapter_date: parse_optional_selector(&selectors.chapter_date)?,
+            chapter_date_formats: self.chapter_date_formats,
+
+            query_pages: parse_selector(&selectors.pages)?,
+        };
+        let info = ConnectorInfo {
+            url: Url::parse(&self.url).map_err(|err| Error::InvalidConnector(err.to_string()))?,
+            id: self.id,
+            label: self.label,
+            tags: self.tags,
+        };
+        Ok((info, selector_info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::mangabox, *};
+
+    #[test]
+    fn listing_cover_stays_within_its_item() {
+        let connector = mangabox::manganato();
+        let dom = Html::parse_document(
+            r#"<div class="panel-content-genres">
+                <div class="genres-item-info">
+                    <h3><a class="genres-item-name" href="/manga-a">A</a></h3>
+                </div>
+                <div class="content-genres-item">
+                    <a class="genres-item-img" href="/manga-b"><img src="/b.jpg"></a>
+                    <div class="genres-item-info">
+                        <h3><a class="genres-item-name" href="/manga-b">B</a></h3>
+                    </div>
+                </div>
+            </div>"#,
+        );
+        let query = &connector.selectors.query_mangas;
+        let mangas: Vec<Manga> = dom
+            .select(query)
+            .filter_map(|element_ref| {
+                let title = element_ref.text().collect::<String>();
+                connector.listing_manga(element_ref, query, &title)
+            })
+            .collect();
+
+        assert_eq!(mangas.len(), 2);
+        assert_eq!(mangas[0].url.as_ref(), "https://manganato.com/manga-a");
+        // The list holding both links is not searched for A's cover.
+        assert_eq!(mangas[0].icon_url.as_ref(), "");
+        assert_eq!(mangas[1].icon_url.as_ref(), "https://manganato.com/b.jpg");
+    }
+}
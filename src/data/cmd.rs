@@ -1,18 +1,73 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
-use druid::Selector;
+use druid::{im::Vector, Selector};
 
-use crate::core::{Chapter, Manga};
+use crate::core::{error::Error, export::VolumeFormat, Chapter, Manga};
 
-use super::Nav;
+use super::{DownloadProgress, DownloadedChapter, DownloadedManga, Nav};
 
 pub const NAVIGATE: Selector<Nav> = Selector::new("app.navigates");
+pub const SHOW_ERROR: Selector<Error> = Selector::new("app.show-error");
+/// Tells how a background task went, above the current route.
+pub const SHOW_NOTICE: Selector<String> = Selector::new("app.show-notice");
 
 pub const DOWNLOAD_CHAPTER: Selector<Chapter> = Selector::new("app.download-chapter");
-pub const UPDATE_DOWNLOAD_PROGRESS: Selector<(Chapter, f64)> =
+/// Sent by a running job, a few times per second at most.
+pub const UPDATE_DOWNLOAD_PROGRESS: Selector<(Chapter, DownloadProgress)> =
     Selector::new("app.update-download-progress");
+/// Starts the next job of the queue unless one is running.
 pub const START_DOWNLOAD: Selector = Selector::new("app.start-download");
-pub const POP_QUEUE: Selector = Selector::new("app.pop-queue");
+/// Sent by the running job of this chapter url when it ends.
+pub const DOWNLOAD_FINISHED: Selector<(Arc<str>, Result<(), Error>)> =
+    Selector::new("app.download-finished");
+/// Pauses (`true`) or resumes the job of this chapter url.
+pub const PAUSE_DOWNLOAD: Selector<(Arc<str>, bool)> = Selector::new("app.pause-download");
+/// Pauses (`true`) or resumes every job of this manga url.
+pub const PAUSE_MANGA_DOWNLOADS: Selector<(Arc<str>, bool)> =
+    Selector::new("app.pause-manga-downloads");
+pub const PAUSE_ALL_DOWNLOADS: Selector<bool> = Selector::new("app.pause-all-downloads");
+/// Queues the failed job of this chapter url again.
+pub const RETRY_DOWNLOAD: Selector<Arc<str>> = Selector::new("app.retry-download");
+pub const CLEAR_DONE_DOWNLOADS: Selector = Selector::new("app.clear-done-downloads");
+pub const CANCEL_DOWNLOAD: Selector<Arc<str>> = Selector::new("app.cancel-download");
+pub const CANCEL_MANGA_DOWNLOADS: Selector<Arc<str>> = Selector::new("app.cancel-manga-downloads");
+/// Moves the job of this chapter url by this many places within its manga.
+pub const MOVE_DOWNLOAD: Selector<(Arc<str>, isize)> = Selector::new("app.move-download");
+/// Moves the jobs of this manga url by this many places among the other manga.
+pub const MOVE_MANGA_DOWNLOADS: Selector<(Arc<str>, isize)> =
+    Selector::new("app.move-manga-downloads");
+/// Re-checks the files of a downloaded chapter and downloads broken pages
+/// again.
+pub const VERIFY_CHAPTER: Selector<Chapter> = Selector::new("app.verify-chapter");
+/// Lists the download folder into `AppState::downloaded`.
+pub const SCAN_DOWNLOADS: Selector = Selector::new("app.scan-downloads");
+pub const DOWNLOADS_SCANNED: Selector<Vector<DownloadedManga>> =
+    Selector::new("app.downloads-scanned");
+pub const OPEN_DOWNLOAD_FOLDER: Selector<PathBuf> = Selector::new("app.open-download-folder");
+/// Deletes a downloaded manga or chapter folder.
+pub const DELETE_DOWNLOAD: Selector<PathBuf> = Selector::new("app.delete-download");
+/// Deletes a downloaded chapter and queues it again.
+pub const REDOWNLOAD_CHAPTER: Selector<DownloadedChapter> = Selector::new("app.redownload-chapter");
+/// Archives a downloaded chapter folder, keeping the folder.
+pub const CONVERT_TO_CBZ: Selector<DownloadedChapter> = Selector::new("app.convert-to-cbz");
+pub const CLEAR_DOWNLOAD_HISTORY: Selector = Selector::new("app.clear-download-history");
+/// Bundles the downloaded ones of these chapters into one volume file.
+pub const EXPORT_VOLUME: Selector<(Vector<Chapter>, VolumeFormat)> =
+    Selector::new("app.export-volume");
+
+pub const ADD_TO_LIBRARY: Selector<Manga> = Selector::new("app.add-to-library");
+pub const REMOVE_FROM_LIBRARY: Selector<Arc<str>> = Selector::new("app.remove-from-library");
+pub const SET_CHAPTER_READ: Selector<(Chapter, bool)> = Selector::new("app.set-chapter-read");
+pub const SET_WATCH: Selector<(Arc<str>, bool)> = Selector::new("app.set-watch");
+
+pub const CHECK_UPDATES: Selector = Selector::new("app.check-updates");
+pub const UPDATES_FOUND: Selector<(Arc<str>, Vector<Chapter>)> = Selector::new("app.updates-found");
+pub const UPDATES_CHECKED: Selector = Selector::new("app.updates-checked");
+
+/// Moves the reader by this many pages in reading order.
+pub const TURN_PAGE: Selector<isize> = Selector::new("app.reader.turn-page");
+/// Opens the next (`1`) or previous (`-1`) chapter in the reader.
+pub const TURN_CHAPTER: Selector<isize> = Selector::new("app.reader.turn-chapter");
 
 pub const FETCH_MANGA_DETAIL: Selector<Arc<str>> = Selector::new("app.fetch-manga-detail");
 pub const LOAD_MANGA_DETAIL: Selector<Manga> = Selector::new("app.load-manga-detail");
@@ -1,21 +1,85 @@
-use std::{fs::File, path::PathBuf};
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
 
+use chrono::Utc;
 use druid::{Data, Lens};
 use platform_dirs::AppDirs;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::core::cache::mkdir_if_not_exists;
+use crate::core::{
+    cache::mkdir_if_not_exists,
+    error::{map_to_string, Error},
+    export::{write_atomic, OutputFormat},
+    http::HttpConfig,
+};
 
-use super::Nav;
+use super::{DownloadConfig, Nav};
 
 const APP_NAME: &str = "Mangetsu";
 const CONFIG_FILENAME: &str = "config.json";
+const CONNECTORS_DIRNAME: &str = "connectors";
 
 #[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
     pub theme: Theme,
     pub last_route: Option<Nav>,
+    /// Languages chapters are fetched in, for connectors serving translations.
+    #[data(same_fn = "PartialEq::eq")]
+    pub chapter_languages: Vec<String>,
+    /// Rate limits and retries of every request, read at startup.
+    #[data(same_fn = "PartialEq::eq")]
+    pub http: HttpConfig,
+    #[data(same_fn = "PartialEq::eq")]
+    pub updates: UpdateConfig,
+    pub reader: ReaderConfig,
+    pub output_format: OutputFormat,
+    #[data(same_fn = "PartialEq::eq")]
+    pub downloads: DownloadConfig,
+    /// Limits of the cover and page image loader, read at startup.
+    #[data(same_fn = "PartialEq::eq")]
+    pub images: ImageConfig,
+}
+
+/// Schedule of the background check for new chapters of watched manga.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    pub enabled: bool,
+    pub interval_minutes: u64,
+    /// Enqueue every new chapter for download as soon as it is found.
+    pub auto_download: bool,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_minutes: 60,
+            auto_download: false,
+        }
+    }
+}
+
+/// How the images of covers and pages are fetched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageConfig {
+    /// Images fetched at once.
+    pub concurrency: usize,
+    /// Images fetched ahead of the viewport.
+    pub prefetch: u64,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 6,
+            prefetch: 8,
+        }
+    }
 }
 
 impl Default for Config {
@@ -23,6 +87,13 @@ impl Default for Config {
         Self {
             theme: Default::default(),
             last_route: Default::default(),
+            chapter_languages: vec!["en".to_string()],
+            http: Default::default(),
+            updates: Default::default(),
+            reader: Default::default(),
+            output_format: Default::default(),
+            downloads: Default::default(),
+            images: Default::default(),
         }
     }
 }
@@ -42,6 +113,11 @@ impl Config {
         Self::app_dirs().map(|dirs| dirs.config_dir)
     }
 
+    /// Folder holding user connector definitions, see `SelectorConnector::load_dir`.
+    pub fn connectors_dir() -> Option<PathBuf> {
+        Self::config_dir().map(|dir| dir.join(CONNECTORS_DIRNAME))
+    }
+
     pub fn download_dir() -> Option<PathBuf> {
         platform_dirs::UserDirs::new().map(|dir| dir.document_dir.join(APP_NAME))
     }
@@ -50,6 +126,52 @@ impl Config {
         Self::config_dir().map(|dir| dir.join(CONFIG_FILENAME))
     }
 
+    /// Reads the json file `name` in the config dir. A file that cannot be
+    /// parsed is moved aside, the next save would overwrite it otherwise.
+    pub fn load_json<T: DeserializeOwned>(name: &str) -> Option<T> {
+        let path = Self::config_dir()?.join(name);
+        let file = File::open(&path).ok()?;
+        log::info!("loading {:?}", &path);
+        let err = match serde_json::from_reader(file) {
+            Ok(value) => return Some(value),
+            Err(err) => err,
+        };
+        let mut aside = path.clone().into_os_string();
+        aside.push(format!(".{}.corrupt", Utc::now().format("%Y%m%d%H%M%S")));
+        log::error!(
+            "failed to read {:?}: {}, moving it to {:?}",
+            &path,
+            err,
+            &aside
+        );
+        if let Err(err) = fs::rename(&path, &aside) {
+            log::error!("failed to move {:?} aside: {}", &path, err);
+        }
+        None
+    }
+
+    /// Writes `value` to the json file `name` in the config dir, through a
+    /// temporary file so a crash never leaves half of it.
+    pub fn save_json<T: Serialize>(name: &str, value: &T) {
+        let path = match Self::config_dir() {
+            Some(dir) => dir.join(name),
+            None => return log::error!("failed to get config dir to save {}", name),
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), mkdir_if_not_exists)
+            .map_err(map_to_string(Error::IoError))
+            .and_then(|_| {
+                write_atomic(&path, |file| {
+                    serde_json::to_writer_pretty(file, value).map_err(map_to_string(Error::IoError))
+                })
+            });
+        match result {
+            Ok(()) => log::info!("saved {:?}", &path),
+            Err(err) => log::error!("failed to save {:?}: {}", &path, err),
+        }
+    }
+
     pub fn load() -> Option<Config> {
         let path = Self::config_path().expect("Failed to get config path");
         if let Ok(file) = File::open(&path) {
@@ -90,3 +212,60 @@ impl std::fmt::Display for Theme {
         })
     }
 }
+
+#[derive(Clone, Debug, Default, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReaderConfig {
+    pub mode: ReadingMode,
+    /// Right to left page turns, for paged mode.
+    pub right_to_left: bool,
+    pub zoom: Zoom,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize, Default)]
+pub enum ReadingMode {
+    /// One page at a time.
+    #[default]
+    Paged,
+    /// Every page stacked in one continuous scroll, for webtoons.
+    Vertical,
+}
+
+impl std::fmt::Display for ReadingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Paged => "Paged",
+            Self::Vertical => "Vertical",
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize, Default)]
+pub enum Zoom {
+    #[default]
+    FitWidth,
+    /// Fits a page in the window height. Vertical mode shows pages at their
+    /// original size instead.
+    FitHeight,
+    Original,
+}
+
+impl Zoom {
+    pub fn next(self) -> Self {
+        match self {
+            Self::FitWidth => Self::FitHeight,
+            Self::FitHeight => Self::Original,
+            Self::Original => Self::FitWidth,
+        }
+    }
+}
+
+impl std::fmt::Display for Zoom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::FitWidth => "Fit width",
+            Self::FitHeight => "Fit height",
+            Self::Original => "Original",
+        })
+    }
+}
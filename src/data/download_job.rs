@@ -1,39 +1,366 @@
-use std::fs::{File, OpenOptions};
-use std::io::Write;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
 
-use druid::{Data, ExtEventSink, Lens, Target};
-use futures::StreamExt;
-use reqwest::Response;
-use reqwest::{header::REFERER, Url};
+use chrono::{DateTime, Utc};
+use druid::{im::Vector, image, Data, Lens};
+use futures::{stream, TryStreamExt};
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use reqwest::{
+    header::{RANGE, REFERER},
+    StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
 use verbatim::PathExt;
+use zip::ZipArchive;
 
 use crate::core::cache::mkdir_if_not_exists;
 use crate::core::error::map_to_string;
-use crate::core::{error::Error, Chapter, GlobalAPI};
-use crate::data::cmd;
+use crate::core::export::{
+    cbz::COMIC_INFO_FILENAME, cbz_path, export_chapter, export_volume, volume_title, write_atomic,
+    OutputFormat, VolumeFormat,
+};
+use crate::core::http::Response;
+use crate::core::{
+    error::Error, local, local::METADATA_FILENAME, page_files, Chapter, Connectors, GlobalAPI,
+    Manga, Page,
+};
 
-use super::Config;
+use super::{
+    downloads::format_size,
+    path_template::{PathTemplate, DEFAULT_TEMPLATE},
+    Config, MyIndexMap,
+};
 
-#[derive(Data, Clone, Lens)]
+const DOWNLOAD_QUEUE_FILENAME: &str = "download_queue.json";
+/// Queue saves started, numbering each save.
+static QUEUE_SNAPSHOTS: AtomicU64 = AtomicU64::new(0);
+/// Number of the last queue save written.
+static QUEUE_WRITTEN: Lazy<Mutex<u64>> = Lazy::new(|| Mutex::new(0));
+/// Held while writing the `metadata.json` of a manga.
+static MANGA_METADATA: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+/// How often a running download reports its progress.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// Span of the rolling transfer rate.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// How many downloads run at once.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadConfig {
+    /// Chapters downloaded at once.
+    pub chapters: usize,
+    /// Lower limits of chapters downloaded at once from a connector, keyed by
+    /// its id as listed by `mangetsu connectors`, e.g. `mangadex`.
+    pub connectors: HashMap<String, usize>,
+    /// Pages of a chapter downloaded at once.
+    pub pages: usize,
+    /// Attempts at a page past the first before its chapter fails.
+    pub page_retries: u32,
+    /// Folder chapters are downloaded to, `Documents/Mangetsu` when unset.
+    /// Downloaded manga are listed from there, read at startup.
+    pub directory: Option<PathBuf>,
+    /// Path of pages under `directory`, see [`DEFAULT_TEMPLATE`].
+    pub template: String,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            chapters: 2,
+            connectors: HashMap::new(),
+            pages: 4,
+            page_retries: 3,
+            directory: None,
+            template: DEFAULT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+impl DownloadConfig {
+    /// Chapters of `connector` downloaded at once.
+    pub fn chapters_for(&self, connector: &Connectors) -> usize {
+        GlobalAPI::global()
+            .connector(connector)
+            .ok()
+            .and_then(|connector| {
+                self.connectors
+                    .get(&connector.get_connector_info().id)
+                    .copied()
+            })
+            .unwrap_or(self.chapters)
+            .min(self.chapters)
+            .max(1)
+    }
+
+    pub fn root(&self) -> Option<PathBuf> {
+        self.directory.clone().or_else(Config::download_dir)
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        PathTemplate::parse(&self.template).map(|_| ())
+    }
+
+    /// The configured template, or the default one when it is invalid.
+    pub fn template(&self) -> PathTemplate {
+        PathTemplate::parse(&self.template).unwrap_or_else(|err| {
+            log::error!("{}, using the default template", err);
+            PathTemplate::default()
+        })
+    }
+}
+
+/// Queued chapters grouped by manga url, downloaded in order.
+pub type DownloadQueue = MyIndexMap<Arc<str>, Vector<DownloadJob>>;
+
+#[derive(Clone, Debug, PartialEq, Data, Serialize, Deserialize, Default)]
+pub enum DownloadStatus {
+    #[default]
+    Queued,
+    Running,
+    /// Skipped until resumed.
+    Paused,
+    /// Stopped on this error, until retried.
+    Failed(String),
+    Done,
+}
+
+impl std::fmt::Display for DownloadStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Queued => f.write_str("Queued"),
+            Self::Running => f.write_str("Downloading"),
+            Self::Paused => f.write_str("Paused"),
+            Self::Failed(reason) => write!(f, "Failed: {}", reason),
+            Self::Done => f.write_str("Done"),
+        }
+    }
+}
+
+/// Progress of a download, as reported while it runs.
+#[derive(Clone, Debug, Default, PartialEq, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadProgress {
+    /// Pages downloaded, of `total_pages`.
+    pub pages: usize,
+    pub total_pages: usize,
+    /// Bytes of the pages so far, of `expected_bytes`.
+    pub bytes: u64,
+    /// Sizes of the pages served so far.
+    pub expected_bytes: u64,
+    /// Bytes per second over the last few seconds.
+    #[serde(skip)]
+    pub rate: f64,
+    /// Downloaded fraction, each page weighing the same.
+    pub fraction: f64,
+}
+
+impl DownloadProgress {
+    /// Marks every page as downloaded.
+    pub fn finish(&mut self) {
+        self.pages = self.total_pages;
+        self.bytes = self.expected_bytes.max(self.bytes);
+        self.rate = 0.;
+        self.fraction = 1.;
+    }
+}
+
+/// `12/40 · 1.2 MB/s`, the rate while transferring.
+impl std::fmt::Display for DownloadProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.total_pages == 0 {
+            return Ok(());
+        }
+        write!(f, "{}/{}", self.pages, self.total_pages)?;
+        if self.rate > 0. {
+            write!(f, " · {}/s", format_size(self.rate as u64))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Data, Clone, Lens, Serialize, Deserialize)]
 pub struct DownloadJob {
     pub chapter: Chapter,
-    pub progress: f64,
+    #[serde(default)]
+    pub progress: DownloadProgress,
+    #[serde(default)]
+    pub status: DownloadStatus,
+    /// When the running download started, for its time left.
+    #[serde(skip)]
+    #[data(same_fn = "PartialEq::eq")]
+    pub started: Option<DateTime<Utc>>,
 }
 
 impl DownloadJob {
     pub fn new(chapter: Chapter) -> Self {
         Self {
             chapter,
-            progress: 0.,
+            progress: DownloadProgress::default(),
+            status: DownloadStatus::Queued,
+            started: None,
         }
     }
 
-    pub fn with_progress(chapter: Chapter, progress: f64) -> Self {
-        Self { chapter, progress }
+    /// Time left at the pace so far.
+    pub fn eta(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        let elapsed = (now - self.started?).num_milliseconds();
+        let fraction = self.progress.fraction;
+        (fraction > 0. && fraction < 1.).then(|| {
+            chrono::Duration::milliseconds((elapsed as f64 * (1. - fraction) / fraction) as i64)
+        })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.status == DownloadStatus::Paused
+    }
+
+    /// Whether the job is waiting for or being downloaded.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.status,
+            DownloadStatus::Queued | DownloadStatus::Running
+        )
     }
 }
 
+impl DownloadQueue {
+    /// Whether this chapter has a job that is not done.
+    pub fn is_pending(&self, chapter_url: &str) -> bool {
+        self.jobs().any(|job| {
+            job.chapter.url.as_ref() == chapter_url && job.status != DownloadStatus::Done
+        })
+    }
+
+    pub fn jobs(&self) -> impl Iterator<Item = &DownloadJob> {
+        self.0.values().flatten()
+    }
+
+    /// Jobs waiting to be started, in download order.
+    pub fn queued_jobs(&self) -> impl Iterator<Item = &DownloadJob> {
+        self.jobs()
+            .filter(|job| job.status == DownloadStatus::Queued)
+    }
+
+    pub fn push(&mut self, job: DownloadJob) {
+        self.0
+            .entry(job.chapter.manga.url.clone())
+            .or_default()
+            .push_back(job);
+    }
+
+    pub fn remove(&mut self, chapter_url: &str) {
+        for jobs in self.0.values_mut() {
+            jobs.retain(|job| job.chapter.url.as_ref() != chapter_url);
+        }
+        self.0.retain(|_, jobs| !jobs.is_empty());
+    }
+
+    pub fn remove_manga(&mut self, manga_url: &str) {
+        self.0.shift_remove(manga_url);
+    }
+
+    /// Applies `update` to the job of this chapter.
+    pub fn update_job(&mut self, chapter_url: &str, update: impl FnOnce(&mut DownloadJob)) {
+        if let Some(job) = self
+            .0
+            .values_mut()
+            .flat_map(|jobs| jobs.iter_mut())
+            .find(|job| job.chapter.url.as_ref() == chapter_url)
+        {
+            update(job);
+        }
+    }
+
+    /// Pauses the active jobs or resumes the paused ones for which `filter`
+    /// holds.
+    pub fn set_paused(&mut self, paused: bool, filter: impl Fn(&DownloadJob) -> bool) {
+        for job in self.0.values_mut().flat_map(|jobs| jobs.iter_mut()) {
+            if !filter(job) {
+                continue;
+            }
+            if paused && job.is_active() {
+                job.status = DownloadStatus::Paused;
+            } else if !paused && job.is_paused() {
+                job.status = DownloadStatus::Queued;
+            }
+        }
+    }
+
+    /// Drops the jobs that are done.
+    pub fn clear_done(&mut self) {
+        for jobs in self.0.values_mut() {
+            jobs.retain(|job| job.status != DownloadStatus::Done);
+        }
+        self.0.retain(|_, jobs| !jobs.is_empty());
+    }
+
+    /// Moves a job `delta` places within its manga.
+    pub fn move_job(&mut self, chapter_url: &str, delta: isize) {
+        for jobs in self.0.values_mut() {
+            if let Some(index) = jobs
+                .iter()
+                .position(|job| job.chapter.url.as_ref() == chapter_url)
+            {
+                let target = index as isize + delta;
+                if target >= 0 && (target as usize) < jobs.len() {
+                    jobs.swap(index, target as usize);
+                }
+                return;
+            }
+        }
+    }
+
+    /// Moves the jobs of a manga `delta` places among the other manga.
+    pub fn move_manga(&mut self, manga_url: &str, delta: isize) {
+        if let Some(index) = self.0.get_index_of(manga_url) {
+            let target = index as isize + delta;
+            if target >= 0 && (target as usize) < self.0.len() {
+                self.0.swap_indices(index, target as usize);
+            }
+        }
+    }
+}
+
+pub fn load_download_queue() -> Option<DownloadQueue> {
+    let jobs: Vec<DownloadJob> = Config::load_json(DOWNLOAD_QUEUE_FILENAME)?;
+    let mut queue = MyIndexMap(IndexMap::new());
+    for mut job in jobs {
+        // Interrupted by the last exit.
+        if job.status == DownloadStatus::Running {
+            job.status = DownloadStatus::Queued;
+        }
+        queue.push(job);
+    }
+    Some(queue)
+}
+
+/// Saves the jobs not done yet on a blocking task, off the UI thread. Saves
+/// are written one at a time, and one older than the last written is dropped.
+pub fn save_download_queue(queue: &DownloadQueue) {
+    let jobs: Vec<DownloadJob> = queue
+        .jobs()
+        .filter(|job| job.status != DownloadStatus::Done)
+        .cloned()
+        .collect();
+    let snapshot = QUEUE_SNAPSHOTS.fetch_add(1, Ordering::SeqCst) + 1;
+    tokio::task::spawn_blocking(move || {
+        let mut written = QUEUE_WRITTEN.lock().unwrap();
+        if *written < snapshot {
+            Config::save_json(DOWNLOAD_QUEUE_FILENAME, &jobs);
+            *written = snapshot;
+        }
+    });
+}
+
 fn to_verbatim(path: &Path) -> PathBuf {
     if cfg!(target_os = "windows") {
         path.to_verbatim()
@@ -42,72 +369,562 @@ fn to_verbatim(path: &Path) -> PathBuf {
     }
 }
 
-fn download_path(chapter: &Chapter) -> Result<PathBuf, Error> {
-    if let Some(download_dir) = Config::download_dir() {
-        let manga_path =
-            download_dir.join(&sanitize_filename::sanitize(chapter.manga.title.as_ref()));
-        let chapter_path =
-            to_verbatim(&manga_path.join(&sanitize_filename::sanitize(chapter.title.as_ref())));
-        mkdir_if_not_exists(&chapter_path).map_err(map_to_string(Error::IoError))?;
-        File::create(manga_path.join("metadata.json"))
-            .and_then(|mut file| file.write(&serde_json::to_vec_pretty(&chapter.manga).unwrap()))
-            .map_err(map_to_string(Error::IoError))?;
-        File::create(chapter_path.join("metadata.json"))
-            .and_then(|mut file| file.write(&serde_json::to_vec_pretty(chapter).unwrap()))
+/// Folder a chapter is or would be downloaded to, next to its `.cbz`.
+fn chapter_path(chapter: &Chapter, config: &DownloadConfig) -> Option<PathBuf> {
+    if chapter.connector == Connectors::Local {
+        let path = Url::parse(&chapter.url).ok()?.to_file_path().ok()?;
+        // Archived chapters are served at the url of their `.cbz`.
+        Some(if path.extension() == Some(OsStr::new("cbz")) {
+            path.with_extension("")
+        } else {
+            path
+        })
+    } else {
+        Some(to_verbatim(
+            &config.template().chapter_dir(&config.root()?, chapter),
+        ))
+    }
+}
+
+/// Pages of a chapter folder that are not whole images are removed. Returns
+/// how many pages were removed or are missing between the others.
+fn verify_dir(dir: &Path, chapter: &Chapter, template: &PathTemplate) -> Result<usize, Error> {
+    let mut broken = 0;
+    let mut indices = Vec::new();
+    for path in page_files(dir)? {
+        if is_valid_image(&path) {
+            let name = path.file_name().and_then(|name| name.to_str());
+            if let Some(index) = name.and_then(|name| template.page_index(chapter, name)) {
+                indices.push(index);
+            }
+        } else {
+            log::warn!("removing broken page {:?}", path);
+            fs::remove_file(&path).map_err(map_to_string(Error::IoError))?;
+            broken += 1;
+        }
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    let gaps = indices
+        .last()
+        .map(|last| (last + 1).saturating_sub(indices.len()))
+        .unwrap_or(0);
+    // Broken pages before the last whole one are gaps too.
+    Ok(gaps.max(broken))
+}
+
+/// A `.cbz` with a page that is not a whole image is removed. Returns how
+/// many pages were broken.
+fn verify_cbz(path: &Path) -> Result<usize, Error> {
+    let file = File::open(path).map_err(map_to_string(Error::IoError))?;
+    let mut archive = ZipArchive::new(file).map_err(map_to_string(Error::IoError))?;
+    let mut broken = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
             .map_err(map_to_string(Error::IoError))?;
-        Ok(chapter_path)
+        if entry.name() == COMIC_INFO_FILENAME {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        let read = entry.read_to_end(&mut bytes).is_ok();
+        if !read || image::load_from_memory(&bytes).is_err() {
+            broken += 1;
+        }
+    }
+    if broken > 0 {
+        log::warn!("removing broken archive {:?}", path);
+        fs::remove_file(path).map_err(map_to_string(Error::IoError))?;
+    }
+    Ok(broken)
+}
+
+/// Re-checks every file of a downloaded chapter, removing broken pages so a
+/// new download fetches them again. Returns how many pages are broken or
+/// missing.
+pub fn verify_chapter(chapter: &Chapter, config: &DownloadConfig) -> Result<usize, Error> {
+    let dir = chapter_path(chapter, config).ok_or(Error::NotFound)?;
+    let cbz = cbz_path(&dir);
+    let mut checked = false;
+    let mut broken = 0;
+    if dir.is_dir() {
+        checked = true;
+        broken += verify_dir(&dir, chapter, &config.template())?;
+    }
+    if cbz.is_file() {
+        checked = true;
+        broken += verify_cbz(&cbz)?;
+    }
+    if !checked {
+        return Err(Error::IoError(format!(
+            "{} is not downloaded",
+            chapter.title
+        )));
+    }
+    Ok(broken)
+}
+
+/// A volume file written by [`export_downloaded`].
+pub struct ExportedVolume {
+    pub path: PathBuf,
+    /// Titles of the chapters left out as they are not downloaded.
+    pub skipped: Vec<Arc<str>>,
+}
+
+/// `Exported to {path}`, followed by the chapters left out.
+impl std::fmt::Display for ExportedVolume {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Exported to {}", self.path.display())?;
+        if !self.skipped.is_empty() {
+            write!(
+                f,
+                ", without {} chapters that are not downloaded: {}",
+                self.skipped.len(),
+                self.skipped.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Bundles the downloaded ones of `chapters`, as folders or `.cbz`, into one
+/// volume file next to them.
+pub fn export_downloaded(
+    chapters: &[Chapter],
+    format: VolumeFormat,
+    config: &DownloadConfig,
+) -> Result<ExportedVolume, Error> {
+    let mut skipped = Vec::new();
+    let chapters: Vec<(Chapter, PathBuf)> = chapters
+        .iter()
+        .filter_map(|chapter| {
+            let dir =
+                chapter_path(chapter, config).filter(|dir| dir.is_dir() || cbz_path(dir).is_file());
+            if dir.is_none() {
+                skipped.push(chapter.title.clone());
+            }
+            Some((chapter.clone(), dir?))
+        })
+        .collect();
+    let manga_dir = match chapters.first() {
+        Some((_, dir)) => dir.parent().map(Path::to_owned),
+        None => {
+            return Err(Error::IoError(
+                "None of these chapters are downloaded".to_string(),
+            ))
+        }
+    }
+    .ok_or(Error::NotFound)?;
+    let title = volume_title(
+        &chapters
+            .iter()
+            .map(|(chapter, _)| chapter.clone())
+            .collect::<Vec<_>>(),
+    );
+    let output = manga_dir.join(format!(
+        "{}.{}",
+        sanitize_filename::sanitize(&title),
+        format.extension()
+    ));
+    export_volume(&title, &chapters, format, &output)?;
+    Ok(ExportedVolume {
+        path: output,
+        skipped,
+    })
+}
+
+/// Writes `value` as the `metadata.json` of `dir`, unless it holds it already.
+fn write_metadata<T: Serialize>(dir: &Path, value: &T) -> Result<(), Error> {
+    let json = serde_json::to_vec_pretty(value).map_err(map_to_string(Error::IoError))?;
+    let path = dir.join(METADATA_FILENAME);
+    if fs::read(&path).ok().as_deref() == Some(json.as_slice()) {
+        return Ok(());
+    }
+    write_atomic(&path, |mut file| {
+        file.write_all(&json).map_err(map_to_string(Error::IoError))
+    })
+}
+
+/// Creates the folders of `chapter` along with their `metadata.json`, returns
+/// the manga and chapter folders.
+fn download_path(chapter: &Chapter, config: &DownloadConfig) -> Result<(PathBuf, PathBuf), Error> {
+    if let Some(root) = config.root() {
+        let template = config.template();
+        let manga_path = to_verbatim(&template.manga_dir(&root, chapter));
+        let chapter_path = to_verbatim(&template.chapter_dir(&root, chapter));
+        mkdir_if_not_exists(&chapter_path).map_err(map_to_string(Error::IoError))?;
+        {
+            // Chapters of a manga downloading at once share its metadata.
+            let _writing = MANGA_METADATA.lock().unwrap();
+            write_metadata(&manga_path, &chapter.manga)?;
+        }
+        write_metadata(&chapter_path, chapter)?;
+        Ok((manga_path, chapter_path))
     } else {
         Err(Error::IoError("Failed to get Download Path".to_string()))
     }
 }
 
-pub async fn start_download(chapter: &Chapter, event_sink: ExtEventSink) -> Result<(), Error> {
-    let pages = chapter.get_pages().enumerate().collect::<Vec<_>>().await;
-    let mut final_chunk_size = 0.;
-    let mut chunk_progress = 0.;
-    for (index, res) in &pages {
-        if let Ok(page) = res {
-            let download_path = download_path(chapter)?;
-            let page_name = Path::new(page.url.as_ref())
-                .extension()
-                .map(|extention| format!("{}.{}", index, extention.to_string_lossy().as_ref()))
-                .expect("Invalid Page URL");
-            let mut file = OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open(download_path.join(page_name))
-                .map_err(map_to_string(Error::IoError))?;
-            let mut request: Response = GlobalAPI::global()
-                .client
-                .get(Url::parse(&page.url).unwrap())
-                .header(REFERER, page.referer.as_ref())
-                .send()
-                .await
-                .map_err(map_to_string(Error::RequestFail))?;
-            final_chunk_size += request
-                .content_length()
-                .map(|content| content as f64)
-                .unwrap_or(0.);
-            while let Some(chunk) = request
-                .chunk()
-                .await
-                .map_err(map_to_string(Error::IoError))?
-            {
-                chunk_progress += chunk.len() as f64;
-                let progress = chunk_progress / final_chunk_size * ((*index as f64) + 1.) * 100.
-                    / pages.len() as f64
-                    / 100.;
-                event_sink
-                    .submit_command(
-                        cmd::UPDATE_DOWNLOAD_PROGRESS,
-                        (chapter.clone(), progress),
-                        Target::Auto,
-                    )
-                    .unwrap();
-                file.write_all(&chunk.to_vec()).unwrap();
+/// Saves the cover of `manga` in its folder `dir`, where the `Local`
+/// connector serves it from.
+async fn save_cover(manga: &Manga, dir: &Path) -> Result<(), Error> {
+    let http = &GlobalAPI::global().http;
+    let bytes = http
+        .send(
+            http.get(manga.icon_url.as_ref())
+                .header(REFERER, manga.url.as_ref()),
+        )
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let extension = image::guess_format(&bytes)
+        .ok()
+        .and_then(|format| format.extensions_str().first().copied())
+        .unwrap_or("jpg");
+    let path = dir.join(format!("{}.{}", local::COVER_STEM, extension));
+    write_atomic(&path, |mut file| {
+        file.write_all(&bytes)
+            .map_err(map_to_string(Error::IoError))
+    })
+}
+
+/// Whether `path` holds a whole image.
+fn is_valid_image(path: &Path) -> bool {
+    image::io::Reader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map(|reader| reader.decode().is_ok())
+        .unwrap_or(false)
+}
+
+async fn is_valid_image_async(path: PathBuf) -> bool {
+    tokio::task::spawn_blocking(move || is_valid_image(&path))
+        .await
+        .unwrap_or(false)
+}
+
+/// Requests a page, from byte `from` on when resuming.
+async fn page_request(page: &Page, from: u64) -> Result<Response, Error> {
+    let http = &GlobalAPI::global().http;
+    let mut request = http
+        .get(page.url.as_ref())
+        .header(REFERER, page.referer.as_ref());
+    if from > 0 {
+        request = request.header(RANGE, format!("bytes={}-", from));
+    }
+    http.send(request).await
+}
+
+/// What happened to a page of a running download.
+enum PageEvent {
+    /// The page is served from byte `offset` on, `size` bytes in all.
+    Started {
+        offset: u64,
+        size: Option<u64>,
+    },
+    Received(u64),
+    /// The page is whole on disk, `size` bytes.
+    Done(u64),
+    /// The page failed and is requested again.
+    Failed,
+}
+
+#[derive(Clone, Copy, Default)]
+struct PageProgress {
+    received: u64,
+    size: Option<u64>,
+    done: bool,
+}
+
+/// Sums up the pages of a running download.
+struct ProgressTracker {
+    pages: Vec<PageProgress>,
+    /// Bytes received by this run, resumed parts aside.
+    transferred: u64,
+    /// Recent `transferred` counts, for the rolling rate.
+    samples: VecDeque<(Instant, u64)>,
+    reported: Option<Instant>,
+}
+
+impl ProgressTracker {
+    fn new(pages: usize) -> Self {
+        Self {
+            pages: vec![PageProgress::default(); pages],
+            transferred: 0,
+            samples: VecDeque::new(),
+            reported: None,
+        }
+    }
+
+    fn apply(&mut self, index: usize, event: PageEvent) {
+        let page = &mut self.pages[index];
+        match event {
+            PageEvent::Started { offset, size } => {
+                page.received = offset;
+                page.size = size;
+            }
+            PageEvent::Received(bytes) => {
+                page.received += bytes;
+                self.transferred += bytes;
+            }
+            PageEvent::Done(size) => {
+                *page = PageProgress {
+                    received: size,
+                    size: Some(size),
+                    done: true,
+                }
             }
+            PageEvent::Failed => *page = PageProgress::default(),
+        }
+    }
+
+    /// Whether to report now, at most every `PROGRESS_INTERVAL` but always
+    /// once every page is done.
+    fn is_due(&mut self, now: Instant) -> bool {
+        let due = self.pages.iter().all(|page| page.done)
+            || !matches!(self.reported, Some(reported) if now - reported < PROGRESS_INTERVAL);
+        if due {
+            self.reported = Some(now);
+        }
+        due
+    }
+
+    fn progress(&mut self, now: Instant) -> DownloadProgress {
+        self.samples.push_back((now, self.transferred));
+        while self.samples.len() > 1 && now - self.samples[0].0 > RATE_WINDOW {
+            self.samples.pop_front();
+        }
+        let (since, transferred) = self.samples[0];
+        let elapsed = (now - since).as_secs_f64();
+        let rate = if elapsed > 0. {
+            (self.transferred - transferred) as f64 / elapsed
+        } else {
+            0.
+        };
+        let fraction = self
+            .pages
+            .iter()
+            .map(|page| match page.size {
+                _ if page.done => 1.,
+                Some(size) if size > 0 => (page.received as f64 / size as f64).min(1.),
+                _ => 0.,
+            })
+            .sum::<f64>()
+            / self.pages.len().max(1) as f64;
+        DownloadProgress {
+            pages: self.pages.iter().filter(|page| page.done).count(),
+            total_pages: self.pages.len(),
+            bytes: self.pages.iter().map(|page| page.received).sum(),
+            expected_bytes: self.pages.iter().filter_map(|page| page.size).sum(),
+            rate,
+            fraction,
+        }
+    }
+}
+
+/// Why a page download stopped.
+enum PageError {
+    /// The request failed or was refused, after the retries of [`Http::send`].
+    ///
+    /// [`Http::send`]: crate::core::http::Http::send
+    Request(Error),
+    /// The body was cut short or could not be written, worth another try.
+    Transfer(Error),
+}
+
+impl From<Error> for PageError {
+    fn from(err: Error) -> Self {
+        Self::Transfer(err)
+    }
+}
+
+/// Downloads one page to `dir` through a `.part` file renamed once whole,
+/// telling `report` about it as chunks arrive. Pages already downloaded are
+/// kept and interrupted ones are resumed when the server allows it.
+async fn download_page(
+    page: &Page,
+    page_name: &str,
+    dir: &Path,
+    report: &impl Fn(PageEvent),
+) -> Result<(), PageError> {
+    let path = dir.join(page_name);
+    let file_size = |path: &Path| fs::metadata(path).map(|meta| meta.len()).ok();
+    if is_valid_image_async(path.clone()).await {
+        report(PageEvent::Done(file_size(&path).unwrap_or_default()));
+        return Ok(());
+    }
+    let temp = dir.join(format!("{}.part", page_name));
+    let partial = file_size(&temp).unwrap_or(0);
+
+    let response = page_request(page, partial)
+        .await
+        .map_err(PageError::Request)?;
+    let (mut response, offset) = match response.status() {
+        StatusCode::PARTIAL_CONTENT => (response, partial),
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            // Frees the host permit held by the response for the next request.
+            drop(response);
+            // Usually the part is whole already.
+            if is_valid_image_async(temp.clone()).await {
+                fs::rename(&temp, &path).map_err(map_to_string(Error::IoError))?;
+                report(PageEvent::Done(partial));
+                return Ok(());
+            }
+            let response = page_request(page, 0)
+                .await
+                .and_then(Response::error_for_status)
+                .map_err(PageError::Request)?;
+            (response, 0)
         }
+        _ => (response.error_for_status().map_err(PageError::Request)?, 0),
+    };
+    let size = response.content_length().map(|size| offset + size);
+    // A page the image decoder cannot read, but as large as served.
+    if offset == 0 && size.is_some() && file_size(&path) == size {
+        report(PageEvent::Done(size.unwrap_or_default()));
+        return Ok(());
     }
+    report(PageEvent::Started { offset, size });
 
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(offset > 0)
+        .truncate(offset == 0)
+        .open(&temp)
+        .map_err(map_to_string(Error::IoError))?;
+    let mut written = offset;
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)
+            .map_err(map_to_string(Error::IoError))?;
+        written += chunk.len() as u64;
+        report(PageEvent::Received(chunk.len() as u64));
+    }
+    file.sync_all().map_err(map_to_string(Error::IoError))?;
+    drop(file);
+
+    let whole = match size {
+        Some(size) => written == size,
+        None => is_valid_image_async(temp.clone()).await,
+    };
+    if !whole {
+        if size.is_none() {
+            let _ = fs::remove_file(&temp);
+        }
+        return Err(PageError::Transfer(Error::RequestFail(format!(
+            "{} was cut short",
+            page_name
+        ))));
+    }
+    fs::rename(&temp, &path).map_err(map_to_string(Error::IoError))?;
+    report(PageEvent::Done(written));
     Ok(())
 }
+
+/// Downloads a page, trying again up to `retries` times with a growing delay
+/// when its transfer fails. Failed requests were retried by the http layer
+/// already.
+async fn download_page_with_retries(
+    page: &Page,
+    page_name: &str,
+    dir: &Path,
+    retries: u32,
+    report: impl Fn(PageEvent),
+) -> Result<(), Error> {
+    let mut attempt = 0;
+    loop {
+        match download_page(page, page_name, dir, &report).await {
+            Ok(()) => return Ok(()),
+            Err(PageError::Transfer(err)) if attempt < retries => {
+                attempt += 1;
+                log::warn!(
+                    "page {} failed ({}), retrying {}/{}",
+                    page_name,
+                    err,
+                    attempt,
+                    retries
+                );
+                report(PageEvent::Failed);
+                tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+            }
+            Err(PageError::Request(err)) | Err(PageError::Transfer(err)) => return Err(err),
+        }
+    }
+}
+
+/// Downloads `chapter` and stores it as `format`, passing its progress to
+/// `on_progress` a few times per second.
+pub async fn start_download(
+    chapter: &Chapter,
+    format: OutputFormat,
+    config: &DownloadConfig,
+    on_progress: impl Fn(DownloadProgress),
+) -> Result<(), Error> {
+    let pages: Vec<Page> = chapter.get_pages().try_collect().await?;
+    if pages.is_empty() {
+        return Err(Error::NotFound);
+    }
+    let (manga_dir, dir) = download_path(chapter, config)?;
+    if local::cover_file(&manga_dir).is_none() && !chapter.manga.icon_url.is_empty() {
+        if let Err(err) = save_cover(&chapter.manga, &manga_dir).await {
+            log::warn!(
+                "failed to save the cover of {}: {}",
+                chapter.manga.title,
+                err
+            );
+        }
+    }
+    let template = config.template();
+    let tracker = Mutex::new(ProgressTracker::new(pages.len()));
+    let report_due = |event: Option<(usize, PageEvent)>| {
+        let now = Instant::now();
+        let progress = {
+            let mut tracker = tracker.lock().unwrap();
+            if let Some((index, event)) = event {
+                tracker.apply(index, event);
+            }
+            if !tracker.is_due(now) {
+                return;
+            }
+            tracker.progress(now)
+        };
+        on_progress(progress);
+    };
+    let report = |index: usize, event: PageEvent| report_due(Some((index, event)));
+    // Reports between chunks too, so the rate of a stalled download drops.
+    let ticker = async {
+        let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+        loop {
+            interval.tick().await;
+            report_due(None);
+        }
+    };
+    let downloads = stream::iter(pages.iter().enumerate().map(Ok)).try_for_each_concurrent(
+        config.pages.max(1),
+        |(index, page)| {
+            let dir = &dir;
+            let report = &report;
+            let extension = Path::new(page.url.as_ref())
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_string())
+                .unwrap_or_else(|| "jpg".to_string());
+            let page_name = template.page_name(chapter, index, &extension);
+            async move {
+                download_page_with_retries(page, &page_name, dir, config.page_retries, |event| {
+                    report(index, event)
+                })
+                .await
+                .map_err(|err| Error::RequestFail(format!("Page {}: {}", index + 1, err)))
+            }
+        },
+    );
+    tokio::select! {
+        result = downloads => result?,
+        _ = ticker => {}
+    }
+
+    let chapter = chapter.clone();
+    tokio::task::spawn_blocking(move || export_chapter(&chapter, &dir, format))
+        .await
+        .map_err(map_to_string(Error::IoError))?
+}
@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use druid::{im::Vector, Data, Lens};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    error::{map_to_string, Error},
+    export::cbz_path,
+    local::{
+        archive_files, archive_metadata_path, metadata_folders, read_archive_metadata,
+        read_metadata,
+    },
+    Chapter, Manga,
+};
+
+use super::Config;
+
+const HISTORY_FILENAME: &str = "download_history.json";
+/// Oldest records are dropped past this count.
+const MAX_HISTORY: usize = 500;
+
+/// A finished download.
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+pub struct DownloadRecord {
+    pub chapter: Chapter,
+    #[data(same_fn = "PartialEq::eq")]
+    pub finished: DateTime<Utc>,
+    /// Why the download failed, if it did.
+    pub error: Option<String>,
+}
+
+/// Finished downloads, newest first.
+#[derive(Clone, Debug, Default, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadHistory {
+    pub records: Vector<DownloadRecord>,
+}
+
+impl DownloadHistory {
+    pub fn load() -> Option<DownloadHistory> {
+        Config::load_json(HISTORY_FILENAME)
+    }
+
+    pub fn save(&self) {
+        Config::save_json(HISTORY_FILENAME, self)
+    }
+
+    pub fn record(&mut self, chapter: Chapter, result: &Result<(), Error>, now: DateTime<Utc>) {
+        self.records.push_front(DownloadRecord {
+            chapter,
+            finished: now,
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+        self.records.truncate(MAX_HISTORY);
+    }
+}
+
+/// A chapter in the download folder.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct DownloadedChapter {
+    /// The chapter as downloaded, from its original connector.
+    pub chapter: Chapter,
+    #[data(same_fn = "PartialEq::eq")]
+    pub dir: PathBuf,
+    /// Bytes used by the folder and its `.cbz`.
+    pub size: u64,
+    pub has_cbz: bool,
+}
+
+/// A manga in the download folder.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct DownloadedManga {
+    pub manga: Manga,
+    #[data(same_fn = "PartialEq::eq")]
+    pub dir: PathBuf,
+    pub size: u64,
+    pub chapters: Vector<DownloadedChapter>,
+}
+
+impl DownloadedManga {
+    /// Url the Local connector serves this manga at.
+    pub fn local_url(&self) -> Option<Arc<str>> {
+        Url::from_directory_path(&self.dir)
+            .ok()
+            .map(|url| url.as_str().into())
+    }
+}
+
+/// Bytes used by a file or everything below a folder.
+pub fn disk_usage(path: &Path) -> u64 {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return 0,
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// `1.5 MB` and the like.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit + 1 < UNITS.len() {
+        size /= 1024.;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Manga and chapters downloaded to `root`, from the `metadata.json` of their
+/// folders.
+pub fn scan_downloads(root: &Path) -> Result<Vector<DownloadedManga>, Error> {
+    if !root.is_dir() {
+        return Ok(Vector::new());
+    }
+    let mut mangas = Vector::new();
+    for dir in metadata_folders(root)? {
+        let manga: Manga = match read_metadata(&dir) {
+            Ok(manga) => manga,
+            Err(err) => {
+                log::warn!("skipping {:?}: {}", dir, err);
+                continue;
+            }
+        };
+        let mut chapters: Vec<DownloadedChapter> = metadata_folders(&dir)?
+            .into_iter()
+            .filter_map(|chapter_dir| {
+                let chapter: Chapter = read_metadata(&chapter_dir).ok()?;
+                let cbz = cbz_path(&chapter_dir);
+                Some(DownloadedChapter {
+                    chapter,
+                    size: disk_usage(&chapter_dir) + disk_usage(&cbz),
+                    has_cbz: cbz.is_file(),
+                    dir: chapter_dir,
+                })
+            })
+            .collect();
+        // Archived without their folder.
+        for cbz in archive_files(&dir) {
+            let chapter_dir = cbz.with_extension("");
+            if chapter_dir.is_dir() {
+                continue;
+            }
+            match read_archive_metadata(&cbz, &manga) {
+                Ok(chapter) => chapters.push(DownloadedChapter {
+                    chapter,
+                    size: disk_usage(&cbz),
+                    has_cbz: true,
+                    dir: chapter_dir,
+                }),
+                Err(err) => log::warn!("skipping {:?}: {}", cbz, err),
+            }
+        }
+        chapters.sort_by(|a, b| {
+            a.chapter
+                .number
+                .partial_cmp(&b.chapter.number)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        mangas.push_back(DownloadedManga {
+            manga,
+            size: disk_usage(&dir),
+            chapters: chapters.into_iter().collect(),
+            dir,
+        });
+    }
+    Ok(mangas)
+}
+
+/// Removes a downloaded manga or chapter folder, along with the `.cbz` of a
+/// chapter and its metadata.
+pub fn delete_download(dir: &Path) -> Result<(), Error> {
+    let cbz = cbz_path(dir);
+    for file in [archive_metadata_path(&cbz), cbz] {
+        if file.is_file() {
+            fs::remove_file(&file).map_err(map_to_string(Error::IoError))?;
+        }
+    }
+    if dir.is_dir() {
+        fs::remove_dir_all(dir).map_err(map_to_string(Error::IoError))?;
+    }
+    Ok(())
+}
+
+/// Shows `dir` in the file manager.
+pub fn open_folder(dir: &Path) {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    if let Err(err) = Command::new(program).arg(dir).spawn() {
+        log::error!("failed to open {:?}: {}", dir, err);
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use druid::{
+    im::{HashSet, Vector},
+    Data, Lens,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Chapter, Manga};
+
+use super::Config;
+
+const LIBRARY_FILENAME: &str = "library.json";
+/// Oldest updates are dropped past this count.
+const MAX_UPDATES: usize = 500;
+
+fn default_watch() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub manga: Manga,
+    /// Chapters as last fetched, oldest first.
+    #[serde(default)]
+    pub chapters: Vector<Chapter>,
+    /// Urls of the chapters marked as read.
+    #[serde(default)]
+    pub read: HashSet<Arc<str>>,
+    /// Whether the update checker looks for new chapters of this manga.
+    #[serde(default = "default_watch")]
+    pub watch: bool,
+    #[serde(default)]
+    #[data(same_fn = "PartialEq::eq")]
+    pub last_checked: Option<DateTime<Utc>>,
+}
+
+/// A chapter found by the update checker.
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+pub struct ChapterUpdate {
+    pub chapter: Chapter,
+    #[data(same_fn = "PartialEq::eq")]
+    pub found: DateTime<Utc>,
+}
+
+impl LibraryEntry {
+    pub fn unread(&self) -> usize {
+        self.chapters
+            .iter()
+            .filter(|chapter| !self.read.contains(&chapter.url))
+            .count()
+    }
+}
+
+#[derive(Clone, Debug, Default, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Library {
+    pub entries: Vector<LibraryEntry>,
+    /// New chapters of watched manga, newest first.
+    pub updates: Vector<ChapterUpdate>,
+}
+
+impl Library {
+    pub fn load() -> Option<Library> {
+        Config::load_json(LIBRARY_FILENAME)
+    }
+
+    pub fn save(&self) {
+        Config::save_json(LIBRARY_FILENAME, self)
+    }
+
+    pub fn get(&self, manga_url: &str) -> Option<&LibraryEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.manga.url.as_ref() == manga_url)
+    }
+
+    fn get_mut(&mut self, manga_url: &str) -> Option<&mut LibraryEntry> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.manga.url.as_ref() == manga_url)
+    }
+
+    pub fn contains(&self, manga_url: &str) -> bool {
+        self.get(manga_url).is_some()
+    }
+
+    pub fn add(&mut self, manga: Manga, chapters: Vector<Chapter>) {
+        if !self.contains(&manga.url) {
+            self.entries.push_back(LibraryEntry {
+                manga,
+                chapters,
+                read: HashSet::new(),
+                watch: true,
+                last_checked: None,
+            });
+        }
+    }
+
+    pub fn remove(&mut self, manga_url: &str) {
+        self.entries
+            .retain(|entry| entry.manga.url.as_ref() != manga_url);
+        self.updates
+            .retain(|update| update.chapter.manga.url.as_ref() != manga_url);
+    }
+
+    /// Stores the latest chapter list of a manga in the library, returns
+    /// whether anything changed.
+    pub fn update_chapters(&mut self, manga_url: &str, chapters: &Vector<Chapter>) -> bool {
+        match self.get_mut(manga_url) {
+            Some(entry) if &entry.chapters != chapters => {
+                entry.chapters = chapters.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn watched(&self) -> impl Iterator<Item = &LibraryEntry> {
+        self.entries.iter().filter(|entry| entry.watch)
+    }
+
+    pub fn set_watch(&mut self, manga_url: &str, watch: bool) {
+        if let Some(entry) = self.get_mut(manga_url) {
+            entry.watch = watch;
+        }
+    }
+
+    /// Diffs `chapters` against the stored ones by url and records the new
+    /// ones as updates found at `now`. The first check of a manga only stores
+    /// its chapters.
+    pub fn apply_update(
+        &mut self,
+        manga_url: &str,
+        chapters: Vector<Chapter>,
+        now: DateTime<Utc>,
+    ) -> Vec<Chapter> {
+        let entry = match self.get_mut(manga_url) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+        let known: std::collections::HashSet<&str> = entry
+            .chapters
+            .iter()
+            .map(|chapter| chapter.url.as_ref())
+            .collect();
+        let new_chapters: Vec<Chapter> = if entry.chapters.is_empty() {
+            Vec::new()
+        } else {
+            chapters
+                .iter()
+                .filter(|chapter| !known.contains(chapter.url.as_ref()))
+                .cloned()
+                .collect()
+        };
+        entry.chapters = chapters;
+        entry.last_checked = Some(now);
+
+        for chapter in new_chapters.iter() {
+            self.updates.push_front(ChapterUpdate {
+                chapter: chapter.clone(),
+                found: now,
+            });
+        }
+        self.updates.truncate(MAX_UPDATES);
+        new_chapters
+    }
+
+    pub fn set_read(&mut self, manga_url: &str, chapter_url: Arc<str>, read: bool) {
+        if let Some(entry) = self.get_mut(manga_url) {
+            if read {
+                entry.read.insert(chapter_url);
+            } else {
+                entry.read.remove(&chapter_url);
+            }
+        }
+    }
+}
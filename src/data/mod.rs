@@ -1,7 +1,12 @@
 pub mod cmd;
 mod config;
 mod download_job;
+mod downloads;
+mod library;
 mod nav;
+mod path_template;
+mod reader;
+mod updates;
 
 use std::{
     hash::Hash,
@@ -9,42 +14,100 @@ use std::{
 };
 
 use druid::{
-    im::{vector, Vector},
+    im::{vector, HashSet, Vector},
     widget::ListIter,
     Data, Lens, WindowState,
 };
 use indexmap::IndexMap;
 use lru_cache::LruCache;
 
-pub use config::{Config, Theme};
-pub use download_job::{start_download, DownloadJob};
+pub use config::{Config, ImageConfig, ReadingMode, Theme, UpdateConfig, Zoom};
+pub use download_job::{
+    export_downloaded, load_download_queue, save_download_queue, start_download, verify_chapter,
+    DownloadConfig, DownloadJob, DownloadProgress, DownloadQueue, DownloadStatus,
+};
+pub use downloads::{
+    delete_download, format_size, open_folder, scan_downloads, DownloadHistory, DownloadRecord,
+    DownloadedChapter, DownloadedManga,
+};
+pub use library::{ChapterUpdate, Library, LibraryEntry};
 pub use nav::Nav;
+pub use reader::ReaderState;
+pub use updates::check_updates;
 
-use crate::core::{Chapter, Manga};
+use crate::core::{error::Error, Chapter, Connectors, Manga};
 
 #[derive(Data, Lens, Clone)]
 pub struct MangaDetail {
     pub manga: Manga,
-    pub start: u16,
-    pub end: u16,
+    pub start: f32,
+    pub end: f32,
     pub chapters: Vector<Chapter>,
+    pub in_library: bool,
+    /// Whether the library entry is watched for new chapters.
+    pub watch: bool,
+    /// Urls of the chapters marked as read, mirrors the library entry.
+    pub read: HashSet<Arc<str>>,
+}
+
+impl MangaDetail {
+    pub fn new(manga: Manga, library: &Library) -> Self {
+        let entry = library.get(&manga.url);
+        Self {
+            in_library: entry.is_some(),
+            watch: entry.map(|entry| entry.watch).unwrap_or_default(),
+            read: entry.map(|entry| entry.read.clone()).unwrap_or_default(),
+            chapters: entry
+                .map(|entry| entry.chapters.clone())
+                .unwrap_or_default(),
+            manga,
+            start: 0.,
+            end: 0.,
+        }
+    }
+}
+
+#[derive(Data, Lens, Clone)]
+pub struct SearchGroup {
+    pub connector: Connectors,
+    pub mangas: Vector<Manga>,
 }
 
 #[derive(Data, Lens, Clone)]
 pub struct AppState {
     pub config: Config,
     pub route: Nav,
+    /// Bumped on every navigation, so navigating to the current route
+    /// rebuilds it.
+    pub visit: u64,
     pub manga_detail: Option<MangaDetail>,
+    pub reader: Option<ReaderState>,
     pub mangas: Vector<Manga>,
     pub manga_cache: Arc<Mutex<LruCache<Arc<str>, Manga>>>,
     pub manga_chapters_cache: Arc<Mutex<LruCache<Arc<str>, Vec<Manga>>>>,
-    pub manga_search_url: String,
-    pub download_queue: MyIndexMap<Arc<str>, Vector<DownloadJob>>,
+    pub search_query: String,
+    pub search_results: Vector<SearchGroup>,
+    pub library: Library,
+    /// Whether an update check is running.
+    pub checking_updates: bool,
+    /// Last error reported by a connector, shown above the current route.
+    pub error: Option<Error>,
+    /// Outcome of the last background task, shown above the current route.
+    pub notice: Option<String>,
+    /// Saved on every change and restored at launch.
+    pub download_queue: DownloadQueue,
+    /// Finished downloads, saved on every change.
+    pub download_history: DownloadHistory,
+    /// Contents of the download folder, listed when opening the downloads.
+    pub downloaded: Vector<DownloadedManga>,
     pub window_state: Arc<WindowState>,
 }
 
 impl AppState {
     pub fn navigate(&mut self, nav: &Nav) {
+        self.visit += 1;
+        self.error = None;
+        self.notice = None;
         if &self.route != nav {
             let _previous = std::mem::replace(&mut self.route, nav.to_owned());
             self.config.last_route.replace(nav.to_owned());
@@ -64,17 +127,32 @@ impl Default for AppState {
                 url: "Hello".into(),
                 icon_url: "Hello".into(),
                 connector: crate::core::Connectors::Manganel,
+                details: None,
             },
+            number: None,
+            volume: None,
+            uploaded: None,
+            scanlator: None,
+            views: None,
         };
         Self {
             config: Config::load().unwrap_or_default(),
             route: Nav::Home,
+            visit: 0,
             manga_detail: None,
+            reader: None,
             mangas: vector![],
             manga_cache: Arc::new(Mutex::new(LruCache::new(256))),
             manga_chapters_cache: Arc::new(Mutex::new(LruCache::new(256))),
-            manga_search_url: Default::default(),
-            download_queue: MyIndexMap(IndexMap::new()),
+            search_query: Default::default(),
+            search_results: vector![],
+            library: Library::load().unwrap_or_default(),
+            checking_updates: false,
+            error: None,
+            notice: None,
+            download_queue: load_download_queue().unwrap_or_else(|| MyIndexMap(IndexMap::new())),
+            download_history: DownloadHistory::load().unwrap_or_default(),
+            downloaded: Vector::new(),
             window_state: Arc::new(WindowState::MAXIMIZED),
         }
     }
@@ -8,15 +8,23 @@ use super::AppState;
 #[derive(Clone, Debug, Data, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Nav {
     Home,
+    Library,
+    Updates,
     Downloads,
+    Search(Arc<str>),
     MangaPage(Arc<str>),
+    /// Reader of the chapter with this url.
+    Reader(Arc<str>),
 }
 
 impl Nav {
     pub fn title(&self, data: &AppState) -> String {
         match self {
             Nav::Home => "Home".to_string(),
+            Nav::Library => "Library".to_string(),
+            Nav::Updates => "Updates".to_string(),
             Nav::Downloads => "Downloads".to_string(),
+            Nav::Search(query) => query.to_string(),
             Nav::MangaPage(manga) => data
                 .manga_cache
                 .lock()
@@ -25,13 +33,21 @@ impl Nav {
                 .map(|manga| manga.title.clone())
                 .unwrap_or_else(|| "Manga Not Found".into())
                 .to_string(),
+            Nav::Reader(_) => data
+                .reader
+                .as_ref()
+                .map(|reader| reader.chapter.title.to_string())
+                .unwrap_or_else(|| "Chapter Not Found".into()),
         }
     }
 
     pub fn full_title(&self, data: &AppState) -> String {
         match self {
             Nav::Home => "Home".to_string(),
+            Nav::Library => "Library".to_string(),
+            Nav::Updates => "Updates".to_string(),
             Nav::Downloads => "Downloads".to_string(),
+            Nav::Search(query) => format!("Search - {}", query),
             Nav::MangaPage(manga) => data
                 .manga_cache
                 .lock()
@@ -39,6 +55,11 @@ impl Nav {
                 .get_mut(manga)
                 .map(|manga| format!("Manga - {} - {}", manga.title, manga.connector))
                 .unwrap_or_else(|| "Manga Not Found".into()),
+            Nav::Reader(_) => data
+                .reader
+                .as_ref()
+                .map(|reader| format!("{} - {}", reader.chapter.manga.title, reader.chapter.title))
+                .unwrap_or_else(|| "Chapter Not Found".into()),
         }
     }
 }
@@ -0,0 +1,398 @@
+use std::path::{Path, PathBuf};
+
+use crate::core::{error::Error, Chapter};
+
+/// Where chapters are downloaded, e.g.
+/// `{connector}/{manga}/{volume} - Ch.{number:04} {title}/{page:03}.{ext}`.
+/// The last component names page files, the others chapter folders. The
+/// default is the layout of downloads made before templates.
+pub const DEFAULT_TEMPLATE: &str = "{manga}/{title}/{index}.{ext}";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Connector,
+    Manga,
+    Volume,
+    Number,
+    Title,
+    /// Page number, from 1.
+    Page,
+    /// Page number, from 0.
+    Index,
+    Ext,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "connector" => Self::Connector,
+            "manga" => Self::Manga,
+            "volume" => Self::Volume,
+            "number" => Self::Number,
+            "title" => Self::Title,
+            "page" => Self::Page,
+            "index" => Self::Index,
+            "ext" => Self::Ext,
+            _ => return None,
+        })
+    }
+
+    fn is_chapter(self) -> bool {
+        matches!(self, Self::Volume | Self::Number | Self::Title)
+    }
+
+    fn is_page(self) -> bool {
+        matches!(self, Self::Page | Self::Index)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Text(String),
+    /// A field, zero padded to a width.
+    Field(Field, usize),
+}
+
+type Component = Vec<Part>;
+
+/// A parsed download template.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathTemplate {
+    folders: Vec<Component>,
+    page: Component,
+    /// Folders up to the manga folder, where its `metadata.json` goes.
+    manga_depth: usize,
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidTemplate(reason.into())
+}
+
+fn parse_component(component: &str) -> Result<Component, Error> {
+    let mut parts = Vec::new();
+    let mut rest = component;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            parts.push(Part::Text(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| invalid(format!("unclosed `{{` in `{}`", component)))?;
+        let placeholder = &rest[start + 1..end];
+        let (name, width) = match placeholder.split_once(':') {
+            Some((name, width)) => (
+                name,
+                width
+                    .parse()
+                    .map_err(|_| invalid(format!("bad width in `{{{}}}`", placeholder)))?,
+            ),
+            None => (placeholder, 0),
+        };
+        let field =
+            Field::parse(name).ok_or_else(|| invalid(format!("unknown field `{{{}}}`", name)))?;
+        if width > 0 && !matches!(field, Field::Number | Field::Page | Field::Index) {
+            return Err(invalid(format!("`{{{}}}` cannot be padded", name)));
+        }
+        parts.push(Part::Field(field, width));
+        rest = &rest[end + 1..];
+    }
+    if rest.contains('}') {
+        return Err(invalid(format!("unopened `}}` in `{}`", component)));
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest.to_string()));
+    }
+    Ok(parts)
+}
+
+fn has(component: &[Part], wanted: impl Fn(Field) -> bool) -> bool {
+    component
+        .iter()
+        .any(|part| matches!(part, Part::Field(field, _) if wanted(*field)))
+}
+
+impl PathTemplate {
+    pub fn parse(template: &str) -> Result<Self, Error> {
+        let mut components = template
+            .split('/')
+            .map(parse_component)
+            .collect::<Result<Vec<_>, Error>>()?;
+        let page = components.pop().unwrap_or_default();
+        let folders = components;
+
+        if !has(&page, Field::is_page) || !has(&page, |field| field == Field::Ext) {
+            return Err(invalid(
+                "the file name needs `{page}` or `{index}`, and `{ext}`",
+            ));
+        }
+        if folders.iter().any(|folder| folder.is_empty()) {
+            return Err(invalid("empty folder name"));
+        }
+        if folders
+            .iter()
+            .any(|folder| has(folder, |field| field.is_page() || field == Field::Ext))
+        {
+            return Err(invalid(
+                "`{page}`, `{index}` and `{ext}` only go in the file name",
+            ));
+        }
+        // The manga folder holds every chapter folder of the manga.
+        let manga_depth = folders
+            .iter()
+            .position(|folder| has(folder, |field| field == Field::Manga))
+            .map(|index| index + 1)
+            .ok_or_else(|| invalid("a folder needs `{manga}`"))?;
+        if folders[..manga_depth]
+            .iter()
+            .any(|folder| has(folder, Field::is_chapter))
+        {
+            return Err(invalid(
+                "chapter fields cannot go in or above the manga folder",
+            ));
+        }
+        let chapter_folders = &folders[manga_depth..];
+        if !chapter_folders.iter().any(|folder| {
+            has(folder, |field| {
+                field == Field::Number || field == Field::Title
+            })
+        }) {
+            return Err(invalid(
+                "a folder below the manga folder needs `{number}` or `{title}`",
+            ));
+        }
+        Ok(Self {
+            folders,
+            page,
+            manga_depth,
+        })
+    }
+
+    fn value(field: Field, width: usize, chapter: &Chapter, page: Option<(usize, &str)>) -> String {
+        let value = match field {
+            Field::Connector => chapter.connector.to_string(),
+            Field::Manga => chapter.manga.title.to_string(),
+            Field::Volume => chapter.volume.as_deref().unwrap_or_default().to_string(),
+            Field::Number => chapter
+                .number
+                .map(|number| pad_number(number, width))
+                .unwrap_or_default(),
+            Field::Title => chapter.title.to_string(),
+            Field::Page => page
+                .map(|(index, _)| format!("{:0width$}", index + 1, width = width))
+                .unwrap_or_default(),
+            Field::Index => page
+                .map(|(index, _)| format!("{:0width$}", index, width = width))
+                .unwrap_or_default(),
+            Field::Ext => page.map(|(_, ext)| ext.to_string()).unwrap_or_default(),
+        };
+        sanitize_filename::sanitize(value)
+    }
+
+    fn render(component: &[Part], chapter: &Chapter, page: Option<(usize, &str)>) -> String {
+        let mut name = String::new();
+        let mut missing = false;
+        for part in component {
+            match part {
+                Part::Text(text) => name.push_str(text),
+                Part::Field(field, width) => {
+                    let value = Self::value(*field, *width, chapter, page);
+                    missing |= value.is_empty();
+                    name.push_str(&value);
+                }
+            }
+        }
+        // Missing fields leave separators dangling.
+        let name = if missing {
+            name.trim_matches(|c: char| c.is_whitespace() || c == '-' || c == '.')
+        } else {
+            &name
+        };
+        if name.is_empty() {
+            "_".to_string()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Folder of the manga of `chapter` under `root`.
+    pub fn manga_dir(&self, root: &Path, chapter: &Chapter) -> PathBuf {
+        self.folders[..self.manga_depth]
+            .iter()
+            .fold(root.to_owned(), |path, folder| {
+                path.join(Self::render(folder, chapter, None))
+            })
+    }
+
+    /// Folder of `chapter` under `root`.
+    pub fn chapter_dir(&self, root: &Path, chapter: &Chapter) -> PathBuf {
+        self.folders[self.manga_depth..]
+            .iter()
+            .fold(self.manga_dir(root, chapter), |path, folder| {
+                path.join(Self::render(folder, chapter, None))
+            })
+    }
+
+    /// File name of the page at `index` of `chapter`.
+    pub fn page_name(&self, chapter: &Chapter, index: usize, extension: &str) -> String {
+        Self::render(&self.page, chapter, Some((index, extension)))
+    }
+
+    /// Index of the page of `chapter` named `name` by [`Self::page_name`].
+    pub fn page_index(&self, chapter: &Chapter, name: &str) -> Option<usize> {
+        let position = self
+            .page
+            .iter()
+            .position(|part| matches!(part, Part::Field(field, _) if field.is_page()))?;
+        let mut prefix = String::new();
+        for part in &self.page[..position] {
+            match part {
+                Part::Text(text) => prefix.push_str(text),
+                Part::Field(field, width) => {
+                    prefix.push_str(&Self::value(*field, *width, chapter, None))
+                }
+            }
+        }
+        // `render` trims the separators of a name with a missing field.
+        let missing = self.page.iter().any(|part| {
+            matches!(part, Part::Field(field, width)
+                if !field.is_page()
+                    && *field != Field::Ext
+                    && Self::value(*field, *width, chapter, None).is_empty())
+        });
+        let prefix = if missing {
+            prefix.trim_start_matches(|c: char| c.is_whitespace() || c == '-' || c == '.')
+        } else {
+            &prefix
+        };
+        let rest = name.strip_prefix(prefix)?;
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number: usize = rest[..end].parse().ok()?;
+        match self.page[position] {
+            Part::Field(Field::Page, _) => number.checked_sub(1),
+            _ => Some(number),
+        }
+    }
+}
+
+impl Default for PathTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_TEMPLATE).expect("Invalid default template")
+    }
+}
+
+/// `10.5` padded to 4 is `0010.5`.
+fn pad_number(number: f32, width: usize) -> String {
+    let number = number.to_string();
+    let (integer, fraction) = match number.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number.as_str(), None),
+    };
+    let mut padded = format!("{:0>width$}", integer, width = width);
+    if let Some(fraction) = fraction {
+        padded.push('.');
+        padded.push_str(fraction);
+    }
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Connectors, Manga};
+
+    fn chapter(number: Option<f32>, volume: Option<&str>) -> Chapter {
+        Chapter {
+            url: "https://example.com/manga/chapter-1".into(),
+            title: "Chapter 1: Start...".into(),
+            connector: Connectors::Manganel,
+            manga: Manga {
+                title: "Some Manga".into(),
+                url: "https://example.com/manga".into(),
+                icon_url: "".into(),
+                connector: Connectors::Manganel,
+                details: None,
+            },
+            number,
+            volume: volume.map(Into::into),
+            uploaded: None,
+            scanlator: None,
+            views: None,
+        }
+    }
+
+    #[test]
+    fn parses_fields_and_text() {
+        assert_eq!(
+            parse_component("Ch.{number:04} {title}").unwrap(),
+            vec![
+                Part::Text("Ch.".to_string()),
+                Part::Field(Field::Number, 4),
+                Part::Text(" ".to_string()),
+                Part::Field(Field::Title, 0),
+            ]
+        );
+        assert!(parse_component("{number").is_err());
+        assert!(parse_component("number}").is_err());
+        assert!(parse_component("{chapter}").is_err());
+        assert!(parse_component("{title:3}").is_err());
+        assert!(parse_component("{page:x}").is_err());
+    }
+
+    #[test]
+    fn default_is_the_old_layout() {
+        let template = PathTemplate::default();
+        let chapter = chapter(Some(1.), None);
+        let root = Path::new("/downloads");
+        assert_eq!(
+            template.chapter_dir(root, &chapter),
+            Path::new("/downloads/Some Manga/Chapter 1 Start...")
+        );
+        assert_eq!(template.page_name(&chapter, 0, "jpg"), "0.jpg");
+        assert_eq!(template.page_name(&chapter, 11, "png"), "11.png");
+    }
+
+    #[test]
+    fn trims_separators_of_missing_fields() {
+        let template =
+            PathTemplate::parse("{manga}/{volume} - Ch.{number:03}/{page:02}.{ext}").unwrap();
+        let root = Path::new("/downloads");
+        assert_eq!(
+            template.chapter_dir(root, &chapter(Some(5.), Some("2"))),
+            Path::new("/downloads/Some Manga/2 - Ch.005")
+        );
+        assert_eq!(
+            template.chapter_dir(root, &chapter(Some(5.), None)),
+            Path::new("/downloads/Some Manga/Ch.005")
+        );
+        assert_eq!(
+            template.chapter_dir(root, &chapter(None, None)),
+            Path::new("/downloads/Some Manga/Ch")
+        );
+        assert_eq!(template.page_name(&chapter(None, None), 0, "jpg"), "01.jpg");
+    }
+
+    #[test]
+    fn reads_back_page_indices() {
+        let chapter = chapter(Some(12.), None);
+        let template =
+            PathTemplate::parse("{manga}/{title}/Ch.{number:03}-{page:02}.{ext}").unwrap();
+        let name = template.page_name(&chapter, 9, "jpg");
+        assert_eq!(template.page_index(&chapter, &name), Some(9));
+        assert_eq!(template.page_index(&chapter, "cover.jpg"), None);
+        let template = PathTemplate::parse("{manga}/{title}/{volume} {index}.{ext}").unwrap();
+        let name = template.page_name(&chapter, 3, "png");
+        assert_eq!(name, "3.png");
+        assert_eq!(template.page_index(&chapter, &name), Some(3));
+    }
+
+    #[test]
+    fn pads_the_integer_part() {
+        assert_eq!(pad_number(10.5, 4), "0010.5");
+        assert_eq!(pad_number(7., 3), "007");
+        assert_eq!(pad_number(1234., 2), "1234");
+        assert_eq!(pad_number(3., 0), "3");
+    }
+}
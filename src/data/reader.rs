@@ -0,0 +1,59 @@
+use druid::{im::Vector, Data, Lens};
+
+use crate::core::{Chapter, Page};
+
+/// The chapter open in the reader.
+#[derive(Clone, Data, Lens)]
+pub struct ReaderState {
+    pub chapter: Chapter,
+    /// Chapters of the manga, oldest first, for next and previous chapter
+    /// transitions.
+    pub chapters: Vector<Chapter>,
+    /// Pages streamed so far.
+    pub pages: Vector<Page>,
+    /// Index of the page shown in paged mode.
+    pub current: usize,
+}
+
+impl ReaderState {
+    pub fn new(chapter: Chapter, chapters: Vector<Chapter>) -> Self {
+        Self {
+            chapter,
+            chapters,
+            pages: Vector::new(),
+            current: 0,
+        }
+    }
+
+    fn index(&self) -> Option<usize> {
+        self.chapters.index_of(&self.chapter)
+    }
+
+    pub fn next_chapter(&self) -> Option<&Chapter> {
+        self.index().and_then(|index| self.chapters.get(index + 1))
+    }
+
+    pub fn previous_chapter(&self) -> Option<&Chapter> {
+        self.index()
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| self.chapters.get(index))
+    }
+
+    pub fn page(&self) -> Option<&Page> {
+        self.pages.get(self.current)
+    }
+
+    pub fn is_last_page(&self) -> bool {
+        !self.pages.is_empty() && self.current + 1 == self.pages.len()
+    }
+
+    /// Moves `delta` pages forward, returns false past either end of the chapter.
+    pub fn turn(&mut self, delta: isize) -> bool {
+        let current = self.current as isize + delta;
+        if current < 0 || current as usize >= self.pages.len() {
+            return false;
+        }
+        self.current = current as usize;
+        true
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use druid::{im::Vector, ExtEventSink, Target};
+use futures::{future, StreamExt};
+
+use crate::core::{error::Error, Chapter, Connectors, Manga};
+use crate::data::cmd;
+
+async fn fetch_chapters(manga: Manga) -> Result<Vector<Chapter>, Error> {
+    let mut chapters = Vector::new();
+    let mut stream = manga.get_chapters();
+    while let Some(res) = stream.next().await {
+        chapters.push_front(res?)
+    }
+    Ok(chapters)
+}
+
+/// Fetches the chapter list of every manga and submits it with
+/// [`cmd::UPDATES_FOUND`]. Manga of the same connector are checked one after
+/// the other so a check never adds more than one request at a time to a
+/// site, on top of the host limits of the request layer.
+pub async fn check_updates(mangas: Vec<Manga>, event_sink: ExtEventSink) {
+    let mut groups: HashMap<Connectors, Vec<Manga>> = HashMap::new();
+    for manga in mangas {
+        groups
+            .entry(manga.connector.clone())
+            .or_default()
+            .push(manga);
+    }
+
+    future::join_all(groups.into_values().map(|mangas| {
+        let event_sink = event_sink.clone();
+        async move {
+            for manga in mangas {
+                let url = manga.url.clone();
+                match fetch_chapters(manga).await {
+                    Ok(chapters) => event_sink
+                        .submit_command(cmd::UPDATES_FOUND, (url, chapters), Target::Auto)
+                        .expect("Command failed to submit"),
+                    Err(err) => log::warn!("failed to check {} for updates: {}", url, err),
+                }
+            }
+        }
+    }))
+    .await;
+
+    event_sink
+        .submit_command(cmd::UPDATES_CHECKED, (), Target::Auto)
+        .expect("Command failed to submit");
+}
@@ -1,39 +1,37 @@
-use std::{
-    io::{copy, Cursor},
-    path::Path,
-    sync::Arc,
-};
-
-use druid::{
-    im::Vector,
-    image::{self, ImageFormat},
-    AppDelegate, Command, DelegateCtx, Env, ExtEventSink, Handled, ImageBuf, Target,
-};
-use lru_cache::LruCache;
-use reqwest::{
-    header::{self, CONTENT_TYPE},
-    Url,
-};
+use chrono::Utc;
+use druid::{AppDelegate, Command, DelegateCtx, Env, ExtEventSink, Handled, Target};
+use reqwest::Url;
+use tokio::task::JoinHandle;
 
 use crate::{
-    core::GlobalAPI,
-    data::{cmd, start_download, AppState, DownloadJob, MangaDetail},
-    widgets::remote_image,
+    core::{
+        error::Error,
+        export::{export_chapter, OutputFormat},
+        Chapter, Connectors, GlobalAPI,
+    },
+    data::{
+        cmd, delete_download, export_downloaded, open_folder, save_download_queue, scan_downloads,
+        start_download, verify_chapter, AppState, DownloadJob, DownloadProgress, DownloadStatus,
+        MangaDetail, Nav,
+    },
 };
 
 pub struct Delegate {
-    image_cache: LruCache<Arc<str>, ImageBuf>,
     event_sink: ExtEventSink,
+    running: Vec<RunningDownload>,
+}
+
+/// The job being downloaded, aborted when paused or cancelled.
+struct RunningDownload {
+    chapter: Chapter,
+    handle: JoinHandle<()>,
 }
 
 impl Delegate {
     pub fn new(event_sink: ExtEventSink) -> Self {
-        const IMAGE_CACHE_SIZE: usize = 256;
-        let image_cache = LruCache::new(IMAGE_CACHE_SIZE);
-
         Self {
-            image_cache,
             event_sink,
+            running: Vec::new(),
         }
     }
 }
@@ -50,38 +48,36 @@ impl AppDelegate<AppState> for Delegate {
         if let Some(manga_url) = cmd.get(cmd::FETCH_MANGA_DETAIL).cloned() {
             let event_sink = self.event_sink.clone();
             tokio::spawn(async move {
-                let connector =
-                    dbg!(GlobalAPI::global()
-                        .connectors
-                        .iter()
-                        .find(|(_, connector)| {
-                            connector.can_handle_uri(Url::parse(manga_url.as_ref()).unwrap())
-                        }));
-                if let Some((_, connector)) = connector {
-                    let manga = connector
-                        .get_manga_from_url(Url::parse(manga_url.as_ref()).unwrap())
-                        .await
-                        .unwrap();
-
-                    event_sink
-                        .submit_command(cmd::LOAD_MANGA_DETAIL, manga, Target::Auto)
-                        .expect("Command failed to submit");
-                }
+                let manga = match GlobalAPI::global().connector_for_url(&manga_url) {
+                    Ok(connector) => match Url::parse(&manga_url) {
+                        Ok(url) => connector.get_manga_from_url(url).await,
+                        Err(err) => Err(Error::RequestFail(err.to_string())),
+                    },
+                    Err(err) => Err(err),
+                };
+                let submitted = match manga {
+                    Ok(manga) => {
+                        event_sink.submit_command(cmd::LOAD_MANGA_DETAIL, manga, Target::Auto)
+                    }
+                    Err(err) => event_sink.submit_command(cmd::SHOW_ERROR, err, Target::Auto),
+                };
+                submitted.expect("Command failed to submit");
             });
             Handled::Yes
+        } else if let Some(error) = cmd.get(cmd::SHOW_ERROR).cloned() {
+            log::error!("{}", error);
+            data.error = Some(error);
+            Handled::Yes
+        } else if let Some(notice) = cmd.get(cmd::SHOW_NOTICE).cloned() {
+            log::info!("{}", notice);
+            data.notice = Some(notice);
+            Handled::Yes
         } else if let Some(manga) = cmd.get(cmd::LOAD_MANGA_DETAIL).cloned() {
             data.manga_cache
                 .lock()
                 .unwrap()
                 .insert(manga.url.clone(), manga.clone());
-            data.manga_detail = Some(MangaDetail {
-                manga,
-                start: 1,
-                end: 1,
-                chapters: Vector::new(),
-            });
-            Handled::Yes
-        } else if let Handled::Yes = self.command_image(ctx, target, cmd, data) {
+            data.manga_detail = Some(MangaDetail::new(manga, &data.library));
             Handled::Yes
         } else {
             self.command_download(ctx, target, cmd, data)
@@ -89,107 +85,42 @@ impl AppDelegate<AppState> for Delegate {
     }
 }
 
-#[derive(Debug)]
-enum ImageRequestError {
-    RequestError(String),
-    ImageLoadError(String),
-}
-
-async fn get_image(
-    uri: &str,
-    referer: Option<&str>,
-) -> Result<image::DynamicImage, ImageRequestError> {
-    let response = GlobalAPI::global()
-        .client
-        .get(uri)
-        .header(header::REFERER, referer.unwrap_or(uri))
-        .send()
-        .await
-        .map_err(|err| ImageRequestError::RequestError(err.to_string()))?;
-    let format = match response
-        .headers()
-        .get(CONTENT_TYPE)
-        .map(|v| v.to_str().unwrap())
-    {
-        Some("image/jpeg") => Some(ImageFormat::Jpeg),
-        Some("image/png") => Some(ImageFormat::Png),
-        _ => None,
-    };
-    let mut content = Cursor::new(
-        response
-            .bytes()
-            .await
-            .map_err(|err| ImageRequestError::RequestError(err.to_string()))?,
-    );
-    let mut body = vec![];
-    copy(&mut content, &mut body).unwrap();
-    let image = if let Some(format) = format {
-        image::load_from_memory_with_format(&body, format)
-            .map_err(|err| ImageRequestError::ImageLoadError(err.to_string()))?
-    } else {
-        image::load_from_memory(&body)
-            .map_err(|err| ImageRequestError::ImageLoadError(err.to_string()))?
-    };
-    Ok(image)
-}
-
 impl Delegate {
-    fn command_image(
-        &mut self,
-        _ctx: &mut DelegateCtx,
-        target: Target,
-        cmd: &Command,
-        _data: &mut AppState,
-    ) -> Handled {
-        if let Some(location) = cmd.get(remote_image::REQUEST_DATA).cloned() {
-            let image_key = Path::new(location.as_ref()).file_name().unwrap().to_owned();
-            let cache = &GlobalAPI::global().cache;
-            if let Some(image_buf) = self.image_cache.get_mut(&location).cloned() {
-                let payload = remote_image::ImagePayload {
-                    location,
-                    image_buf,
-                };
-
-                self.event_sink
-                    .submit_command(remote_image::PROVIDE_DATA, payload, target)
-                    .expect("Command failed to submit");
-            } else if let Some(image_buf) =
-                cache.get_image("images", image_key.to_string_lossy().as_ref())
-            {
-                log::info!("Old Image");
-                let payload = remote_image::ImagePayload {
-                    location,
-                    image_buf,
-                };
-
-                self.event_sink
-                    .submit_command(remote_image::PROVIDE_DATA, payload, target)
-                    .expect("Command failed to submit");
+    /// Aborts the running jobs whose chapter `stop` holds for.
+    fn stop_running(&mut self, stop: impl Fn(&Chapter) -> bool) {
+        self.running.retain(|running| {
+            if stop(&running.chapter) {
+                log::info!("Stopping download of {}", running.chapter.title);
+                running.handle.abort();
+                false
             } else {
-                log::info!("Grabbing Image");
-                let event_sink = self.event_sink.clone();
-                tokio::spawn(async move {
-                    let image_buf: ImageBuf = {
-                        let dyn_image = get_image(&location, None).await.unwrap();
-                        ImageBuf::from_dynamic_image(dyn_image)
-                    };
-                    cache.set_image("images", image_key.to_string_lossy().as_ref(), &image_buf);
-                    let payload = remote_image::ImagePayload {
-                        location,
-                        image_buf,
-                    };
-                    event_sink
-                        .submit_command(remote_image::PROVIDE_DATA, payload, target)
-                        .expect("Command failed to submit");
-                });
+                true
             }
-            Handled::Yes
-        } else if let Some(payload) = cmd.get(remote_image::PROVIDE_DATA).cloned() {
-            self.image_cache.insert(payload.location, payload.image_buf);
-            Handled::No
-        } else {
-            Handled::No
-        }
+        });
+    }
+
+    /// Lists the download folder in the background.
+    fn scan_downloads(&self, data: &AppState) {
+        let root = match data.config.downloads.root() {
+            Some(root) => root,
+            None => return,
+        };
+        let event_sink = self.event_sink.clone();
+        tokio::task::spawn_blocking(move || {
+            let submitted = match scan_downloads(&root) {
+                Ok(downloaded) => {
+                    event_sink.submit_command(cmd::DOWNLOADS_SCANNED, downloaded, Target::Auto)
+                }
+                Err(err) => event_sink.submit_command(cmd::SHOW_ERROR, err, Target::Auto),
+            };
+            submitted.expect("Command failed to submit");
+        });
+    }
+
+    fn start_next(&self) {
+        self.event_sink
+            .submit_command(cmd::START_DOWNLOAD, (), Target::Auto)
+            .unwrap();
     }
 
     fn command_download(
@@ -200,64 +131,268 @@ impl Delegate {
         data: &mut AppState,
     ) -> Handled {
         if let Some(chapter) = cmd.get(cmd::DOWNLOAD_CHAPTER).cloned() {
-            let download_job = DownloadJob::new(chapter.clone());
-            data.download_queue
-                .0
-                .entry(chapter.manga.url)
-                .or_insert(Vector::new())
-                .push_back(download_job);
-
-            if data.download_queue.0.first().unwrap().1.len() == 1 {
-                self.event_sink
-                    .submit_command(cmd::START_DOWNLOAD, (), Target::Auto)
-                    .unwrap();
-            };
+            if chapter.connector == Connectors::Local {
+                log::info!("{} is already downloaded", chapter.title);
+                return Handled::Yes;
+            }
+            if !data.download_queue.is_pending(&chapter.url) {
+                data.download_queue.remove(&chapter.url);
+                data.download_queue.push(DownloadJob::new(chapter));
+                save_download_queue(&data.download_queue);
+                self.start_next();
+            }
             Handled::Yes
         } else if let Some(()) = cmd.get(cmd::START_DOWNLOAD) {
-            if let Some((_, download_queue)) = data.download_queue.0.first() {
-                if let Some(download_job) = download_queue.get(0).cloned() {
-                    let event_sink = self.event_sink.clone();
-                    tokio::spawn(async move {
-                        log::info!(
-                            "Starting download of {}",
-                            download_job.chapter.title.as_ref()
-                        );
-                        if start_download(&download_job.chapter, event_sink.clone())
-                            .await
-                            .is_ok()
-                        {
-                            event_sink
-                                .submit_command(cmd::POP_QUEUE, (), Target::Auto)
-                                .unwrap();
-                            event_sink
-                                .submit_command(cmd::START_DOWNLOAD, (), Target::Auto)
-                                .unwrap();
-                        };
-                    });
+            let config = &data.config.downloads;
+            // Jobs are started in queue order, skipping those whose connector
+            // is at its limit.
+            let jobs: Vec<DownloadJob> = data.download_queue.queued_jobs().cloned().collect();
+            for download_job in jobs {
+                if self.running.len() >= config.chapters.max(1) {
+                    break;
+                }
+                let connector = &download_job.chapter.connector;
+                let connector_running = self
+                    .running
+                    .iter()
+                    .filter(|running| &running.chapter.connector == connector)
+                    .count();
+                if connector_running >= config.chapters_for(connector) {
+                    continue;
                 }
+                let format = data.config.output_format;
+                let config = config.clone();
+                let event_sink = self.event_sink.clone();
+                let chapter = download_job.chapter.clone();
+                let handle = tokio::spawn(async move {
+                    log::info!("Starting download of {}", chapter.title.as_ref());
+                    let result = start_download(&chapter, format, &config, |progress| {
+                        event_sink
+                            .submit_command(
+                                cmd::UPDATE_DOWNLOAD_PROGRESS,
+                                (chapter.clone(), progress),
+                                Target::Auto,
+                            )
+                            .unwrap()
+                    })
+                    .await;
+                    event_sink
+                        .submit_command(
+                            cmd::DOWNLOAD_FINISHED,
+                            (chapter.url.clone(), result),
+                            Target::Auto,
+                        )
+                        .unwrap();
+                });
+                data.download_queue
+                    .update_job(&download_job.chapter.url, |job| {
+                        job.status = DownloadStatus::Running;
+                        job.started = Some(Utc::now());
+                    });
+                self.running.push(RunningDownload {
+                    chapter: download_job.chapter,
+                    handle,
+                });
+            }
+            Handled::Yes
+        } else if let Some((chapter_url, result)) = cmd.get(cmd::DOWNLOAD_FINISHED).cloned() {
+            let running = self
+                .running
+                .iter()
+                .position(|running| running.chapter.url == chapter_url)
+                .map(|index| self.running.remove(index).chapter);
+            // A job paused meanwhile may have finished before it was stopped,
+            // its result stands.
+            let mut queued = None;
+            data.download_queue
+                .update_job(&chapter_url, |job| queued = Some(job.chapter.clone()));
+            let chapter = match running.or(queued) {
+                Some(chapter) => chapter,
+                // Sent by a job cancelled meanwhile.
+                None => return Handled::Yes,
             };
+            data.download_history.record(chapter, &result, Utc::now());
+            data.download_history.save();
+            if result.is_ok() && data.route == Nav::Downloads {
+                self.scan_downloads(data);
+            }
+            data.download_queue
+                .update_job(&chapter_url, |job| match result {
+                    Ok(()) => {
+                        job.progress.finish();
+                        job.status = DownloadStatus::Done;
+                    }
+                    Err(err) => {
+                        log::error!("download of {} failed: {}", job.chapter.title, err);
+                        job.status = DownloadStatus::Failed(err.to_string());
+                    }
+                });
+            save_download_queue(&data.download_queue);
+            self.start_next();
             Handled::Yes
-        } else if let Some(()) = cmd.get(cmd::POP_QUEUE) {
-            if let Some((_, download_queue)) = data.download_queue.0.first_mut() {
-                download_queue.pop_front();
-                if download_queue.is_empty() {
-                    data.download_queue.0.pop();
-                }
+        } else if let Some(chapter) = cmd.get(cmd::VERIFY_CHAPTER).cloned() {
+            let config = data.config.downloads.clone();
+            let event_sink = self.event_sink.clone();
+            tokio::task::spawn_blocking(move || {
+                let submitted = match verify_chapter(&chapter, &config) {
+                    Ok(0) => {
+                        log::info!("{} is whole", chapter.title);
+                        Ok(())
+                    }
+                    Ok(broken) if chapter.connector != Connectors::Local => {
+                        log::info!("{} has {} broken pages", chapter.title, broken);
+                        event_sink.submit_command(cmd::DOWNLOAD_CHAPTER, chapter, Target::Auto)
+                    }
+                    Ok(broken) => event_sink.submit_command(
+                        cmd::SHOW_ERROR,
+                        Error::IoError(format!(
+                            "{} has {} broken or missing pages",
+                            chapter.title, broken
+                        )),
+                        Target::Auto,
+                    ),
+                    Err(err) => event_sink.submit_command(cmd::SHOW_ERROR, err, Target::Auto),
+                };
+                submitted.expect("Command failed to submit");
+            });
+            Handled::Yes
+        } else if let Some(()) = cmd.get(cmd::SCAN_DOWNLOADS) {
+            self.scan_downloads(data);
+            Handled::Yes
+        } else if let Some(downloaded) = cmd.get(cmd::DOWNLOADS_SCANNED).cloned() {
+            data.downloaded = downloaded;
+            Handled::Yes
+        } else if let Some(dir) = cmd.get(cmd::OPEN_DOWNLOAD_FOLDER) {
+            open_folder(dir);
+            Handled::Yes
+        } else if let Some(dir) = cmd.get(cmd::DELETE_DOWNLOAD).cloned() {
+            let event_sink = self.event_sink.clone();
+            tokio::task::spawn_blocking(move || {
+                let submitted = match delete_download(&dir) {
+                    Ok(()) => {
+                        log::info!("Deleted {:?}", dir);
+                        event_sink.submit_command(cmd::SCAN_DOWNLOADS, (), Target::Auto)
+                    }
+                    Err(err) => event_sink.submit_command(cmd::SHOW_ERROR, err, Target::Auto),
+                };
+                submitted.expect("Command failed to submit");
+            });
+            Handled::Yes
+        } else if let Some(downloaded) = cmd.get(cmd::REDOWNLOAD_CHAPTER).cloned() {
+            let event_sink = self.event_sink.clone();
+            tokio::task::spawn_blocking(move || {
+                let submitted = match delete_download(&downloaded.dir) {
+                    Ok(()) => event_sink
+                        .submit_command(cmd::DOWNLOAD_CHAPTER, downloaded.chapter, Target::Auto)
+                        .and_then(|_| {
+                            event_sink.submit_command(cmd::SCAN_DOWNLOADS, (), Target::Auto)
+                        }),
+                    Err(err) => event_sink.submit_command(cmd::SHOW_ERROR, err, Target::Auto),
+                };
+                submitted.expect("Command failed to submit");
+            });
+            Handled::Yes
+        } else if let Some(downloaded) = cmd.get(cmd::CONVERT_TO_CBZ).cloned() {
+            let event_sink = self.event_sink.clone();
+            tokio::task::spawn_blocking(move || {
+                let submitted = match export_chapter(
+                    &downloaded.chapter,
+                    &downloaded.dir,
+                    OutputFormat::Both,
+                ) {
+                    Ok(()) => event_sink.submit_command(cmd::SCAN_DOWNLOADS, (), Target::Auto),
+                    Err(err) => event_sink.submit_command(cmd::SHOW_ERROR, err, Target::Auto),
+                };
+                submitted.expect("Command failed to submit");
+            });
+            Handled::Yes
+        } else if let Some(()) = cmd.get(cmd::CLEAR_DOWNLOAD_HISTORY) {
+            data.download_history.records.clear();
+            data.download_history.save();
+            Handled::Yes
+        } else if let Some((chapters, format)) = cmd.get(cmd::EXPORT_VOLUME).cloned() {
+            let config = data.config.downloads.clone();
+            let event_sink = self.event_sink.clone();
+            tokio::task::spawn_blocking(move || {
+                let chapters: Vec<_> = chapters.into_iter().collect();
+                let submitted = match export_downloaded(&chapters, format, &config) {
+                    Ok(volume) => event_sink.submit_command(
+                        cmd::SHOW_NOTICE,
+                        volume.to_string(),
+                        Target::Auto,
+                    ),
+                    Err(err) => event_sink.submit_command(cmd::SHOW_ERROR, err, Target::Auto),
+                };
+                submitted.expect("Command failed to submit");
+            });
+            Handled::Yes
+        } else if let Some((chapter_url, paused)) = cmd.get(cmd::PAUSE_DOWNLOAD).cloned() {
+            data.download_queue
+                .set_paused(paused, |job| job.chapter.url == chapter_url);
+            if paused {
+                self.stop_running(|chapter| chapter.url == chapter_url);
+            }
+            save_download_queue(&data.download_queue);
+            self.start_next();
+            Handled::Yes
+        } else if let Some((manga_url, paused)) = cmd.get(cmd::PAUSE_MANGA_DOWNLOADS).cloned() {
+            data.download_queue
+                .set_paused(paused, |job| job.chapter.manga.url == manga_url);
+            if paused {
+                self.stop_running(|chapter| chapter.manga.url == manga_url);
+            }
+            save_download_queue(&data.download_queue);
+            self.start_next();
+            Handled::Yes
+        } else if let Some(paused) = cmd.get(cmd::PAUSE_ALL_DOWNLOADS).copied() {
+            data.download_queue.set_paused(paused, |_| true);
+            if paused {
+                self.stop_running(|_| true);
             }
+            save_download_queue(&data.download_queue);
+            self.start_next();
+            Handled::Yes
+        } else if let Some(chapter_url) = cmd.get(cmd::RETRY_DOWNLOAD).cloned() {
+            data.download_queue.update_job(&chapter_url, |job| {
+                job.progress = DownloadProgress::default();
+                job.status = DownloadStatus::Queued;
+            });
+            save_download_queue(&data.download_queue);
+            self.start_next();
+            Handled::Yes
+        } else if let Some(()) = cmd.get(cmd::CLEAR_DONE_DOWNLOADS) {
+            data.download_queue.clear_done();
+            save_download_queue(&data.download_queue);
+            Handled::Yes
+        } else if let Some(chapter_url) = cmd.get(cmd::CANCEL_DOWNLOAD).cloned() {
+            self.stop_running(|chapter| chapter.url == chapter_url);
+            data.download_queue.remove(&chapter_url);
+            save_download_queue(&data.download_queue);
+            self.start_next();
+            Handled::Yes
+        } else if let Some(manga_url) = cmd.get(cmd::CANCEL_MANGA_DOWNLOADS).cloned() {
+            self.stop_running(|chapter| chapter.manga.url == manga_url);
+            data.download_queue.remove_manga(&manga_url);
+            save_download_queue(&data.download_queue);
+            self.start_next();
+            Handled::Yes
+        } else if let Some((chapter_url, delta)) = cmd.get(cmd::MOVE_DOWNLOAD).cloned() {
+            data.download_queue.move_job(&chapter_url, delta);
+            save_download_queue(&data.download_queue);
+            Handled::Yes
+        } else if let Some((manga_url, delta)) = cmd.get(cmd::MOVE_MANGA_DOWNLOADS).cloned() {
+            data.download_queue.move_manga(&manga_url, delta);
+            save_download_queue(&data.download_queue);
             Handled::Yes
         } else if let Some((chapter, progress)) = cmd.get(cmd::UPDATE_DOWNLOAD_PROGRESS).cloned() {
-            let manga_download_queue = &mut data.download_queue.0;
-            if let Some((_, download_queue)) = manga_download_queue.first() {
-                let (index, previous_download_job) = download_queue
-                    .iter()
-                    .enumerate()
-                    .find(|(_index, download_job)| download_job.chapter.url == chapter.url)
-                    .unwrap();
-                let new_download_queue = download_queue.update(
-                    index,
-                    DownloadJob::with_progress(previous_download_job.chapter.clone(), progress),
-                );
-                manga_download_queue.insert(chapter.manga.url, new_download_queue);
+            let mut save = false;
+            data.download_queue.update_job(&chapter.url, |job| {
+                // Saved once per page rather than on every report.
+                save = job.progress.pages != progress.pages;
+                job.progress = progress;
+            });
+            if save {
+                save_download_queue(&data.download_queue);
             }
             Handled::Yes
         } else {
@@ -1,3 +1,4 @@
+mod cli;
 mod controller;
 mod core;
 mod data;
@@ -5,14 +6,18 @@ mod delegate;
 mod ui;
 mod widgets;
 
-use druid::{AppLauncher, WindowDesc};
+use std::time::Duration;
+
+use druid::{AppLauncher, ExtEventSink, Target, WindowDesc};
 use log::{Level, LevelFilter, Metadata, SetLoggerError};
+use structopt::StructOpt;
 
 use crate::core::GlobalAPI;
-use data::{AppState, Config};
+use cli::Cli;
+use data::{cmd, AppState, Config, UpdateConfig};
 use delegate::Delegate;
 use ui::{app_widget, compute_window_title, make_menu, theme};
-use widgets::AsyncDelegate;
+use widgets::{AsyncDelegate, ImageLoader};
 
 struct SimpleLogger;
 impl log::Log for SimpleLogger {
@@ -39,11 +44,54 @@ pub fn init() -> Result<(), SetLoggerError> {
     log::set_boxed_logger(Box::new(SimpleLogger)).map(|()| log::set_max_level(LevelFilter::Info))
 }
 
+/// Submits [`cmd::CHECK_UPDATES`] at launch and then every configured interval.
+fn schedule_update_checks(config: &UpdateConfig, event_sink: ExtEventSink) {
+    if !config.enabled {
+        return;
+    }
+    let interval = Duration::from_secs(config.interval_minutes.max(1) * 60);
+    tokio::spawn(async move {
+        loop {
+            if event_sink
+                .submit_command(cmd::CHECK_UPDATES, (), Target::Auto)
+                .is_err()
+            {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+fn install_global_api(config: &Config) {
+    GlobalAPI::install(
+        Config::cache_dir(),
+        Config::connectors_dir(),
+        config.downloads.root(),
+        config.chapter_languages.clone(),
+        config.http.clone(),
+    );
+}
+
 #[tokio::main]
 async fn main() {
-    GlobalAPI::install(Config::cache_dir());
+    let cli = Cli::from_args();
+    if cli.command.is_some() {
+        let config = Config::load().unwrap_or_default();
+        install_global_api(&config);
+        if let Err(err) = cli::run(cli, config).await {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let state = AppState::default();
+    let mut state = AppState::default();
+    if let Err(err) = state.config.downloads.validate() {
+        state.error = Some(err);
+    }
+
+    install_global_api(&state.config);
 
     let main_window = WindowDesc::new(app_widget)
         .title(compute_window_title)
@@ -55,6 +103,14 @@ async fn main() {
     let app = AppLauncher::with_window(main_window);
 
     let delegate = Delegate::new(app.get_external_handle());
+    ImageLoader::install(app.get_external_handle(), state.config.images.clone());
+    schedule_update_checks(&state.config.updates, app.get_external_handle());
+    if !state.download_queue.0.is_empty() {
+        // Resumes the queue left by the last run.
+        app.get_external_handle()
+            .submit_command(cmd::START_DOWNLOAD, (), Target::Auto)
+            .expect("Command failed to submit");
+    }
 
     init().expect("Failed to setup logger");
 
@@ -1,29 +1,35 @@
 use std::sync::Arc;
 
 use druid::{
-    im::Vector,
-    piet::PaintBrush,
+    lens,
     widget::{
-        Button, CrossAxisAlignment, Either, Flex, Label, List, Painter, Scroll, SizedBox, Split,
+        Button, CrossAxisAlignment, Either, Flex, Label, LineBreaking, Painter, SizedBox, Split,
         TextBox, ViewSwitcher,
     },
-    Application, Color, Insets, LensExt, Rect, RenderContext, Size, Target, Widget, WidgetExt,
-    WindowState,
+    Application, Color, Insets, Rect, RenderContext, Size, Widget, WidgetExt, WindowState,
 };
+use futures::{future, stream};
 use reqwest::Url;
 
 use crate::{
-    controller::NavController,
-    core::{error::Error, Chapter, Connectors, GlobalAPI, Manga},
-    data::{cmd, AppState, DownloadJob, Nav, Theme},
+    controller::{LibraryController, NavController},
+    core::{error::Error, Connectors, GlobalAPI, Manga},
+    data::{cmd, AppState, Nav, Theme},
     theme,
     widgets::{
         icons::{MAXIMIZED, QUIT_APP, RESTORED},
-        MyWidgetExt, ProgressBar, StreamWidget, ThemeScope, TitleBar,
+        Maybe, MyWidgetExt, StreamWidget, ThemeScope, TitleBar,
     },
 };
 
-use super::manga::{manga_page_widget, mangas_widget};
+use super::{
+    downloads::{downloads_widget, manga_download_widget},
+    library::library_widget,
+    manga::{manga_page_widget, mangas_widget},
+    reader::reader_widget,
+    search::search_widget,
+    updates::updates_widget,
+};
 
 fn titlebar() -> impl Widget<AppState> {
     Flex::row()
@@ -135,38 +141,37 @@ pub fn app_widget() -> impl Widget<AppState> {
             Flex::row()
                 .with_child(
                     TextBox::new()
-                        .with_placeholder("Manga URL")
+                        .with_placeholder("Search title or paste manga URL")
                         //.controller()
-                        .lens(AppState::manga_search_url)
+                        .lens(AppState::search_query)
                         .fix_width(theme::grid(50.)),
                 )
                 .with_child(
                     Button::new("Search").on_click(|ctx, data: &mut AppState, _| {
-                        let search_url = data.manga_search_url.clone();
-                        let handle = ctx.get_external_handle();
-                        tokio::spawn(async move {
-                            let connector = dbg!(GlobalAPI::global().connectors.iter().find(
-                                |(_, connector)| {
-                                    connector.can_handle_uri(Url::parse(&search_url).unwrap())
-                                }
-                            ));
-                            if let Some((_, connector)) = connector {
-                                let manga = connector
-                                    .get_manga_from_url(Url::parse(&search_url).unwrap())
-                                    .await
-                                    .unwrap();
-                                handle
-                                    .submit_command(
-                                        cmd::NAVIGATE,
-                                        Nav::MangaPage(manga.url),
-                                        Target::Auto,
-                                    )
-                                    .unwrap();
+                        let search_url = data.search_query.trim().to_string();
+                        if search_url.is_empty() {
+                            return;
+                        }
+                        if Url::parse(&search_url).is_err() {
+                            ctx.submit_command(cmd::NAVIGATE.with(Nav::Search(search_url.into())));
+                            return;
+                        }
+                        match GlobalAPI::global().connector_for_url(&search_url) {
+                            Ok(_) => ctx.submit_command(
+                                cmd::NAVIGATE.with(Nav::MangaPage(search_url.into())),
+                            ),
+                            Err(_) => {
+                                data.error = Some(Error::RequestFail(format!(
+                                    "No connector can open {}",
+                                    search_url
+                                )))
                             }
-                        });
+                        }
                     }),
                 ),
         )
+        .with_child(error_widget())
+        .with_child(notice_widget())
         .with_flex_child(route_widget(), 1.0)
         .background(theme::BACKGROUND_LIGHT);
 
@@ -179,63 +184,20 @@ pub fn app_widget() -> impl Widget<AppState> {
 
     root.add_child(titlebar());
 
-    ThemeScope::new(root.with_flex_child(split, 1.)).controller(NavController)
+    ThemeScope::new(root.with_flex_child(split, 1.))
+        .controller(NavController)
+        .controller(LibraryController)
     //    .debug_invalidation()
     //    .debug_widget_id()
     //    .debug_paint_layout()
 }
 
-fn truncate(string: &str, len: usize) -> String {
-    if string.len() > len {
-        format!("{}...", string[..len].to_string())
-    } else {
-        string.to_string()
-    }
-}
-
-fn download_widget() -> impl Widget<DownloadJob> {
-    Flex::column()
-        .with_child(
-            Label::dynamic(|data: &Chapter, _| truncate(&data.manga.title, 30))
-                .with_text_color(theme::TEXT_COLOR)
-                .lens(DownloadJob::chapter),
-        )
-        .with_child(
-            Flex::row()
-                .with_flex_child(
-                    Label::raw()
-                        .with_text_color(theme::TEXT_COLOR)
-                        .expand_width()
-                        .lens(DownloadJob::chapter.then(Chapter::title)),
-                    1.,
-                )
-                .with_child(
-                    ProgressBar::new()
-                        .with_bar_brush(PaintBrush::Color(Color::RED))
-                        .with_corner_radius(2.)
-                        .with_border_width(2.)
-                        .fix_width(theme::grid(20.))
-                        .align_right()
-                        .lens(DownloadJob::progress),
-                ),
-        )
-        .cross_axis_alignment(CrossAxisAlignment::Start)
-}
-
-fn chapter_download_widget() -> impl Widget<Vector<DownloadJob>> {
-    Scroll::new(List::new(download_widget)).vertical()
-}
-
-fn manga_download_widget() -> impl Widget<AppState> {
-    Scroll::new(List::new(chapter_download_widget).lens(AppState::download_queue))
-        .vertical()
-        .expand_height()
-}
-
 fn sidebar_menu_widget() -> impl Widget<AppState> {
     Flex::column()
         .with_default_spacer()
         .with_child(sidebar_link_widget("Home", Nav::Home))
+        .with_child(sidebar_link_widget("Library", Nav::Library))
+        .with_child(sidebar_link_widget("Updates", Nav::Updates))
         .with_child(sidebar_link_widget("Downloads", Nav::Downloads))
 }
 
@@ -274,27 +236,56 @@ fn sidebar_link_widget(title: &str, nav: Nav) -> impl Widget<AppState> {
 
 fn route_widget() -> impl Widget<AppState> {
     ViewSwitcher::new(
-        |data: &AppState, _| data.route.clone(),
-        |value: &Nav, _, _| match value {
+        |data: &AppState, _| (data.route.clone(), data.visit),
+        |(value, _): &(Nav, u64), _, _| match value {
             Nav::Home => home_widget().boxed(),
-            Nav::Downloads => Label::new("No").boxed(),
+            Nav::Library => library_widget().lens(AppState::library).boxed(),
+            Nav::Updates => updates_widget().boxed(),
+            Nav::Downloads => downloads_widget().boxed(),
+            Nav::Search(_) => search_widget().boxed(),
             Nav::MangaPage(_) => manga_page_widget().lens(AppState::manga_detail).boxed(),
+            Nav::Reader(_) => reader_widget().boxed(),
         },
     )
 }
 
-fn home_widget() -> impl Widget<AppState> {
+fn error_widget() -> impl Widget<AppState> {
+    Maybe::or_empty(|| {
+        Label::dynamic(|error: &Error, _| format!("{} (click to dismiss)", error))
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .with_text_color(theme::RED)
+            .padding(theme::grid(1.))
+            .expand_width()
+    })
+    .lens(AppState::error)
+    .on_click(|_, data: &mut AppState, _| data.error = None)
+}
+
+fn notice_widget() -> impl Widget<AppState> {
+    Maybe::or_empty(|| {
+        Label::dynamic(|notice: &String, _| format!("{} (click to dismiss)", notice))
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .with_text_color(theme::TEXT_COLOR)
+            .padding(theme::grid(1.))
+            .expand_width()
+    })
+    .lens(AppState::notice)
+    .on_click(|_, data: &mut AppState, _| data.notice = None)
+}
+
+/// Mangas listed by `connector` on its first page.
+fn listing_widget(connector: Connectors) -> impl Widget<AppState> {
     StreamWidget::new(
-        mangas_widget().lens(AppState::mangas),
-        |_, _| {
-            GlobalAPI::global()
-                .connectors
-                .get(&Connectors::Manganel)
-                .unwrap()
-                .get_mangas_from_page(1)
+        mangas_widget().lens(lens::Map::new(
+            |data: &AppState| (data.library.clone(), data.mangas.clone()),
+            |data: &mut AppState, (_, mangas)| data.mangas = mangas,
+        )),
+        move |_, _| match GlobalAPI::global().connector(&connector) {
+            Ok(connector) => connector.get_mangas_from_page(1),
+            Err(err) => Box::pin(stream::once(future::ready(Err(err)))),
         },
-        |value: Box<Result<Manga, Error>>, data: &mut AppState, _| {
-            if let Ok(manga) = *value {
+        |value: Box<Result<Manga, Error>>, data: &mut AppState, _| match *value {
+            Ok(manga) => {
                 let inner_manga = manga.clone();
                 data.manga_cache
                     .lock()
@@ -302,6 +293,11 @@ fn home_widget() -> impl Widget<AppState> {
                     .insert(inner_manga.url.clone(), inner_manga);
                 data.mangas.push_front(manga);
             }
+            Err(err) => data.error = Some(err),
         },
     )
 }
+
+fn home_widget() -> impl Widget<AppState> {
+    listing_widget(Connectors::Manganel)
+}
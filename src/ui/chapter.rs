@@ -1,11 +1,18 @@
+use std::sync::Arc;
+
 use druid::{
+    im::HashSet,
+    lens,
     widget::{Button, Flex, Label, List, Scroll},
     UnitPoint, Widget, WidgetExt,
 };
 
 use crate::{
     core::Chapter,
-    data::{cmd::DOWNLOAD_CHAPTER, MangaDetail},
+    data::{
+        cmd::{DOWNLOAD_CHAPTER, NAVIGATE, SET_CHAPTER_READ, VERIFY_CHAPTER},
+        MangaDetail, Nav,
+    },
     widgets::{DynamicSizedBox, MyWidgetExt},
 };
 
@@ -13,11 +20,19 @@ use super::theme;
 
 pub fn chapter_widget() -> impl Widget<Chapter> {
     Flex::row()
+        .with_child(Button::new("Read").on_click(|ctx, data: &mut Chapter, _| {
+            ctx.submit_command(NAVIGATE.with(Nav::Reader(data.url.clone())))
+        }))
         .with_child(
             Button::new("Download").on_click(|ctx, data: &mut Chapter, _| {
                 ctx.submit_command(DOWNLOAD_CHAPTER.with(data.clone()))
             }),
         )
+        .with_child(
+            Button::new("Verify").on_click(|ctx, data: &mut Chapter, _| {
+                ctx.submit_command(VERIFY_CHAPTER.with(data.clone()))
+            }),
+        )
         .with_flex_child(
             DynamicSizedBox::new(
                 Label::raw()
@@ -28,15 +43,71 @@ pub fn chapter_widget() -> impl Widget<Chapter> {
             ),
             1.,
         )
+        .with_child(
+            Label::dynamic(|data: &Chapter, _| chapter_info(data))
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR),
+        )
+}
+
+/// A chapter row along with the urls of the chapters already read, read
+/// chapters are dimmed.
+fn read_chapter_widget() -> impl Widget<(HashSet<Arc<str>>, Chapter)> {
+    Flex::row()
+        .with_flex_child(
+            chapter_widget().lens(lens!((HashSet<Arc<str>>, Chapter), 1)),
+            1.,
+        )
+        .with_child(
+            Button::dynamic(|(read, chapter): &(HashSet<Arc<str>>, Chapter), _| {
+                if read.contains(&chapter.url) {
+                    "Mark unread"
+                } else {
+                    "Mark read"
+                }
+                .to_string()
+            })
+            .on_click(
+                |ctx, (read, chapter): &mut (HashSet<Arc<str>>, Chapter), _| {
+                    let is_read = read.contains(&chapter.url);
+                    ctx.submit_command(SET_CHAPTER_READ.with((chapter.clone(), !is_read)))
+                },
+            ),
+        )
+        .env_scope(|env, (read, chapter): &(HashSet<Arc<str>>, Chapter)| {
+            if read.contains(&chapter.url) {
+                env.set(theme::TEXT_COLOR, env.get(theme::PLACEHOLDER_COLOR));
+            }
+        })
+}
+
+fn chapter_info(chapter: &Chapter) -> String {
+    let mut info = Vec::new();
+    if let Some(volume) = &chapter.volume {
+        info.push(format!("Vol.{}", volume));
+    }
+    if let Some(scanlator) = &chapter.scanlator {
+        info.push(scanlator.to_string());
+    }
+    if let Some(views) = chapter.views {
+        info.push(format!("{} views", views));
+    }
+    if let Some(uploaded) = chapter.uploaded {
+        info.push(uploaded.format("%Y-%m-%d").to_string());
+    }
+    info.join(" · ")
 }
 
 pub fn chapters_widget() -> impl Widget<MangaDetail> {
     Scroll::new(
-        List::new(chapter_widget)
+        List::new(read_chapter_widget)
             .with_spacing(theme::grid(2.))
             .padding_right(theme::grid(1.))
             .expand_width()
-            .lens(MangaDetail::chapters),
+            .lens(lens::Map::new(
+                |detail: &MangaDetail| (detail.read.clone(), detail.chapters.clone()),
+                |detail: &mut MangaDetail, (_, chapters)| detail.chapters = chapters,
+            )),
     )
     .vertical()
     .align_vertical(UnitPoint::TOP)
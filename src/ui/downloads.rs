@@ -0,0 +1,413 @@
+use std::sync::Arc;
+
+use chrono::{Local, Utc};
+use druid::{
+    im::Vector,
+    piet::PaintBrush,
+    widget::{
+        Button, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, Scroll, SizedBox,
+        ViewSwitcher,
+    },
+    Color, Command, Data, LensExt, Widget, WidgetExt,
+};
+
+use crate::{
+    core::{Chapter, Connectors, Manga},
+    data::{
+        cmd, format_size, AppState, DownloadHistory, DownloadJob, DownloadProgress, DownloadRecord,
+        DownloadStatus, DownloadedChapter, DownloadedManga, Nav,
+    },
+    widgets::{ConfirmButton, MyWidgetExt, Overlay, ProgressBar},
+};
+
+use super::theme;
+
+/// The first `len` characters of `string`, followed by `...` when cut.
+fn truncate(string: &str, len: usize) -> String {
+    match string.char_indices().nth(len) {
+        Some((end, _)) => format!("{}...", &string[..end]),
+        None => string.to_string(),
+    }
+}
+
+/// Small button sending the command made by `command` from the data.
+fn download_button<T: Data>(
+    text: &str,
+    command: impl Fn(&T) -> Command + 'static,
+) -> impl Widget<T> {
+    Button::new(text).on_click(move |ctx, data: &mut T, _| ctx.submit_command(command(data)))
+}
+
+/// `1:05:03` or `4:02`.
+fn format_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Status of a job, with its size and time left while running.
+fn job_status(job: &DownloadJob) -> String {
+    let mut status = job.status.to_string();
+    if job.status == DownloadStatus::Running {
+        let progress = &job.progress;
+        if progress.expected_bytes > 0 {
+            status.push_str(&format!(
+                " · {} of {}",
+                format_size(progress.bytes),
+                format_size(progress.expected_bytes)
+            ));
+        }
+        if let Some(eta) = job.eta(Utc::now()) {
+            status.push_str(&format!(" · {} left", format_duration(eta)));
+        }
+    }
+    status
+}
+
+fn download_widget() -> impl Widget<DownloadJob> {
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_flex_child(
+                    Label::raw()
+                        .with_text_color(theme::TEXT_COLOR)
+                        .expand_width()
+                        .lens(DownloadJob::chapter.then(Chapter::title)),
+                    1.,
+                )
+                .with_child(
+                    Overlay::new(
+                        ProgressBar::new()
+                            .with_bar_brush(PaintBrush::Color(Color::RED))
+                            .with_corner_radius(2.)
+                            .with_border_width(2.)
+                            .lens(DownloadProgress::fraction),
+                        Label::dynamic(|data: &DownloadProgress, _| data.to_string())
+                            .with_text_size(theme::TEXT_SIZE_SMALL),
+                    )
+                    .fix_width(theme::grid(20.))
+                    .align_right()
+                    .lens(DownloadJob::progress),
+                ),
+        )
+        .with_child(
+            Label::dynamic(|data: &DownloadJob, _| job_status(data))
+                .with_line_break_mode(LineBreaking::WordWrap)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .env_scope(|env, data: &DownloadJob| {
+                    if let DownloadStatus::Failed(_) = data.status {
+                        env.set(theme::TEXT_COLOR, env.get(theme::RED));
+                    }
+                }),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(ViewSwitcher::new(
+                    |data: &DownloadJob, _| data.status.clone(),
+                    |status: &DownloadStatus, _, _| match status {
+                        DownloadStatus::Paused => {
+                            download_button("Resume", |data: &DownloadJob| {
+                                cmd::PAUSE_DOWNLOAD.with((data.chapter.url.clone(), false))
+                            })
+                            .boxed()
+                        }
+                        DownloadStatus::Failed(_) => {
+                            download_button("Retry", |data: &DownloadJob| {
+                                cmd::RETRY_DOWNLOAD.with(data.chapter.url.clone())
+                            })
+                            .boxed()
+                        }
+                        DownloadStatus::Done => SizedBox::empty().boxed(),
+                        _ => download_button("Pause", |data: &DownloadJob| {
+                            cmd::PAUSE_DOWNLOAD.with((data.chapter.url.clone(), true))
+                        })
+                        .boxed(),
+                    },
+                ))
+                .with_child(download_button("▲", |data: &DownloadJob| {
+                    cmd::MOVE_DOWNLOAD.with((data.chapter.url.clone(), -1))
+                }))
+                .with_child(download_button("▼", |data: &DownloadJob| {
+                    cmd::MOVE_DOWNLOAD.with((data.chapter.url.clone(), 1))
+                }))
+                .with_child(download_button("Cancel", |data: &DownloadJob| {
+                    cmd::CANCEL_DOWNLOAD.with(data.chapter.url.clone())
+                })),
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+/// Manga url of a group of jobs.
+fn jobs_manga_url(jobs: &Vector<DownloadJob>) -> Arc<str> {
+    jobs.front()
+        .map(|job| job.chapter.manga.url.clone())
+        .unwrap_or_else(|| "".into())
+}
+
+fn chapter_download_widget() -> impl Widget<Vector<DownloadJob>> {
+    Flex::column()
+        .with_child(
+            Label::dynamic(|data: &Vector<DownloadJob>, _| {
+                data.front()
+                    .map(|job| truncate(&job.chapter.manga.title, 30))
+                    .unwrap_or_default()
+            })
+            .with_text_color(theme::TEXT_COLOR),
+        )
+        .with_child(
+            Flex::row()
+                .with_child(Either::new(
+                    |data: &Vector<DownloadJob>, _| !data.iter().any(DownloadJob::is_active),
+                    download_button("Resume all", |data: &Vector<DownloadJob>| {
+                        cmd::PAUSE_MANGA_DOWNLOADS.with((jobs_manga_url(data), false))
+                    }),
+                    download_button("Pause all", |data: &Vector<DownloadJob>| {
+                        cmd::PAUSE_MANGA_DOWNLOADS.with((jobs_manga_url(data), true))
+                    }),
+                ))
+                .with_child(download_button("▲", |data: &Vector<DownloadJob>| {
+                    cmd::MOVE_MANGA_DOWNLOADS.with((jobs_manga_url(data), -1))
+                }))
+                .with_child(download_button("▼", |data: &Vector<DownloadJob>| {
+                    cmd::MOVE_MANGA_DOWNLOADS.with((jobs_manga_url(data), 1))
+                }))
+                .with_child(download_button(
+                    "Cancel all",
+                    |data: &Vector<DownloadJob>| {
+                        cmd::CANCEL_MANGA_DOWNLOADS.with(jobs_manga_url(data))
+                    },
+                )),
+        )
+        .with_child(List::new(download_widget).padding((theme::grid(1.), 0., 0., 0.)))
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+/// The queue, shown in the sidebar.
+pub fn manga_download_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(ViewSwitcher::new(
+            |data: &AppState, _| {
+                let jobs = &data.download_queue;
+                (
+                    jobs.jobs().any(DownloadJob::is_active),
+                    jobs.jobs().any(DownloadJob::is_paused),
+                    jobs.jobs().any(|job| job.status == DownloadStatus::Done),
+                )
+            },
+            |&(active, paused, done): &(bool, bool, bool), _, _| {
+                let mut row = Flex::row();
+                if active {
+                    row.add_child(download_button("Pause downloads", |_: &AppState| {
+                        cmd::PAUSE_ALL_DOWNLOADS.with(true)
+                    }));
+                } else if paused {
+                    row.add_child(download_button("Resume downloads", |_: &AppState| {
+                        cmd::PAUSE_ALL_DOWNLOADS.with(false)
+                    }));
+                }
+                if done {
+                    row.add_child(download_button("Clear done", |_: &AppState| {
+                        cmd::CLEAR_DONE_DOWNLOADS.into()
+                    }));
+                }
+                row.boxed()
+            },
+        ))
+        .with_flex_child(
+            Scroll::new(List::new(chapter_download_widget).lens(AppState::download_queue))
+                .vertical()
+                .expand_height(),
+            1.,
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+fn section_title<T: Data>(title: &str) -> impl Widget<T> {
+    Label::new(title)
+        .with_font(theme::UI_FONT_MEDIUM)
+        .with_text_color(theme::TEXT_COLOR)
+        .padding((0., theme::grid(2.), theme::grid(1.), theme::grid(1.)))
+}
+
+fn placeholder<T: Data>(text: &str) -> impl Widget<T> {
+    Label::new(text).with_text_color(theme::PLACEHOLDER_COLOR)
+}
+
+fn small_label<T: Data>(text: impl Fn(&T) -> String + 'static) -> impl Widget<T> {
+    Label::dynamic(move |data: &T, _| text(data))
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+}
+
+fn record_widget() -> impl Widget<DownloadRecord> {
+    Flex::row()
+        .with_flex_child(
+            Label::dynamic(|data: &DownloadRecord, _| {
+                format!("{} - {}", data.chapter.manga.title, data.chapter.title)
+            })
+            .with_text_color(theme::TEXT_COLOR)
+            .expand_width(),
+            1.,
+        )
+        .with_child(
+            Label::dynamic(|data: &DownloadRecord, _| match &data.error {
+                Some(error) => format!("Failed: {}", error),
+                None => "Done".to_string(),
+            })
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .env_scope(|env, data: &DownloadRecord| {
+                if data.error.is_some() {
+                    env.set(theme::TEXT_COLOR, env.get(theme::RED));
+                }
+            }),
+        )
+        .with_spacer(theme::grid(2.))
+        .with_child(small_label(|data: &DownloadRecord| {
+            data.finished
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        }))
+}
+
+fn downloaded_chapter_widget() -> impl Widget<DownloadedChapter> {
+    Flex::row()
+        .with_flex_child(
+            Label::raw()
+                .with_text_color(theme::TEXT_COLOR)
+                .expand_width()
+                .lens(DownloadedChapter::chapter.then(Chapter::title)),
+            1.,
+        )
+        .with_child(small_label(|data: &DownloadedChapter| {
+            if data.has_cbz {
+                format!("{} · CBZ", format_size(data.size))
+            } else {
+                format_size(data.size)
+            }
+        }))
+        .with_default_spacer()
+        .with_child(download_button(
+            "Open folder",
+            |data: &DownloadedChapter| {
+                // Archived chapters have no folder of their own.
+                let dir = if data.dir.is_dir() {
+                    data.dir.clone()
+                } else {
+                    data.dir.parent().unwrap_or(&data.dir).to_owned()
+                };
+                cmd::OPEN_DOWNLOAD_FOLDER.with(dir)
+            },
+        ))
+        .with_child(ConfirmButton::new(
+            "Delete",
+            |ctx, data: &mut DownloadedChapter, _| {
+                ctx.submit_command(cmd::DELETE_DOWNLOAD.with(data.dir.clone()))
+            },
+        ))
+        .with_child(Either::new(
+            |data: &DownloadedChapter, _| data.chapter.connector == Connectors::Local,
+            SizedBox::empty(),
+            download_button("Re-download", |data: &DownloadedChapter| {
+                cmd::REDOWNLOAD_CHAPTER.with(data.clone())
+            }),
+        ))
+        .with_child(Either::new(
+            |data: &DownloadedChapter, _| data.has_cbz,
+            SizedBox::empty(),
+            download_button("Convert to CBZ", |data: &DownloadedChapter| {
+                cmd::CONVERT_TO_CBZ.with(data.clone())
+            }),
+        ))
+}
+
+fn downloaded_manga_widget() -> impl Widget<DownloadedManga> {
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_flex_child(
+                    Label::raw()
+                        .with_font(theme::UI_FONT_MEDIUM)
+                        .with_text_color(theme::TEXT_COLOR)
+                        .lens(DownloadedManga::manga.then(Manga::title))
+                        .on_click(|ctx, data: &mut DownloadedManga, _| {
+                            if let Some(url) = data.local_url() {
+                                ctx.submit_command(cmd::NAVIGATE.with(Nav::MangaPage(url)))
+                            }
+                        })
+                        .expand_width(),
+                    1.,
+                )
+                .with_child(small_label(|data: &DownloadedManga| format_size(data.size)))
+                .with_default_spacer()
+                .with_child(download_button("Open folder", |data: &DownloadedManga| {
+                    cmd::OPEN_DOWNLOAD_FOLDER.with(data.dir.clone())
+                }))
+                .with_child(ConfirmButton::new(
+                    "Delete",
+                    |ctx, data: &mut DownloadedManga, _| {
+                        ctx.submit_command(cmd::DELETE_DOWNLOAD.with(data.dir.clone()))
+                    },
+                )),
+        )
+        .with_child(
+            List::new(downloaded_chapter_widget)
+                .padding((theme::grid(2.), 0., 0., 0.))
+                .lens(DownloadedManga::chapters),
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+/// The queue, past downloads and the contents of the download folder.
+pub fn downloads_widget() -> impl Widget<AppState> {
+    Scroll::new(
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(section_title("Queue"))
+            .with_child(Either::new(
+                |data: &AppState, _| data.download_queue.0.is_empty(),
+                placeholder("Nothing is being downloaded."),
+                List::new(chapter_download_widget)
+                    .with_spacing(theme::grid(1.))
+                    .lens(AppState::download_queue),
+            ))
+            .with_child(Flex::row().with_child(section_title("History")).with_child(
+                download_button("Clear history", |_: &AppState| {
+                    cmd::CLEAR_DOWNLOAD_HISTORY.into()
+                }),
+            ))
+            .with_child(
+                Either::new(
+                    |data: &DownloadHistory, _| data.records.is_empty(),
+                    placeholder("No finished downloads yet."),
+                    List::new(record_widget).lens(DownloadHistory::records),
+                )
+                .lens(AppState::download_history),
+            )
+            .with_child(
+                Flex::row()
+                    .with_child(section_title("Downloaded"))
+                    .with_child(small_label(|data: &AppState| {
+                        format_size(data.downloaded.iter().map(|manga| manga.size).sum())
+                    }))
+                    .with_default_spacer()
+                    .with_child(download_button("Refresh", |_: &AppState| {
+                        cmd::SCAN_DOWNLOADS.into()
+                    })),
+            )
+            .with_child(Either::new(
+                |data: &AppState, _| data.downloaded.is_empty(),
+                placeholder("The download folder is empty."),
+                List::new(downloaded_manga_widget)
+                    .with_spacing(theme::grid(1.))
+                    .lens(AppState::downloaded),
+            ))
+            .padding_right(theme::grid(1.)),
+    )
+    .vertical()
+    .expand_width()
+}
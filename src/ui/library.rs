@@ -0,0 +1,36 @@
+use druid::{
+    widget::{Either, Flex, Label, Scroll},
+    Widget, WidgetExt,
+};
+
+use crate::{
+    data::{Library, LibraryEntry},
+    widgets::GridView,
+};
+
+use super::{
+    manga::{manga_widget, unread_badge},
+    theme,
+};
+
+fn library_entry_widget() -> impl Widget<LibraryEntry> {
+    Flex::column()
+        .with_child(manga_widget().lens(LibraryEntry::manga))
+        .with_child(unread_badge(LibraryEntry::unread))
+}
+
+pub fn library_widget() -> impl Widget<Library> {
+    Either::new(
+        |library: &Library, _| library.entries.is_empty(),
+        Label::new("Your library is empty, add manga from their page.")
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .center(),
+        Scroll::new(
+            GridView::new(library_entry_widget)
+                .wrap()
+                .with_spacing(10.)
+                .lens(Library::entries),
+        )
+        .vertical(),
+    )
+}
@@ -1,19 +1,60 @@
+use std::sync::Arc;
+
 use druid::{
     im::Vector,
+    lens,
     text::format::ParseFormatter,
-    widget::{Button, CrossAxisAlignment, Flex, FlexParams, Label, Scroll, Spinner, TextBox},
-    Color, LensExt, UnitPoint, Widget, WidgetExt,
+    widget::{
+        Button, CrossAxisAlignment, Either, Flex, FlexParams, Label, LineBreaking, Scroll,
+        SizedBox, Spinner, TextBox,
+    },
+    Color, Data, LensExt, UnitPoint, Widget, WidgetExt,
 };
 use futures::StreamExt;
 
 use crate::{
-    core::{error::Error, Chapter, GlobalAPI, Manga},
-    data::{cmd, MangaDetail, Nav},
+    core::{error::Error, export::VolumeFormat, Chapter, GlobalAPI, Manga, MangaDetails},
+    data::{cmd, Library, MangaDetail, Nav},
     widgets::{remote_image::RemoteImage, FutureWidget, GridView, Maybe},
 };
 
 use super::{chapter::chapters_widget, manga, theme};
 
+/// Chapters numbered within the start and end picked on the manga page,
+/// oldest first. A chapter without a number goes with the closest numbered
+/// chapter before it in the list, or after it for the first ones.
+fn chapter_range(data: &MangaDetail) -> Vector<Chapter> {
+    let (start, end) = (data.start, data.end);
+    let mut previous = None;
+    let numbers: Vec<Option<f32>> = data
+        .chapters
+        .iter()
+        .map(|chapter| {
+            previous = chapter.number.or(previous);
+            previous
+        })
+        .collect();
+    let first = data.chapters.iter().find_map(|chapter| chapter.number);
+    data.chapters
+        .iter()
+        .zip(numbers)
+        .filter(|(_, number)| match number.or(first) {
+            Some(number) => number >= start && number <= end,
+            // Nothing to pick by number.
+            None => true,
+        })
+        .map(|(chapter, _)| chapter.clone())
+        .collect()
+}
+
+fn export_button(format: VolumeFormat) -> impl Widget<MangaDetail> {
+    Button::new(format!("Export {}", format))
+        .on_click(move |ctx, data: &mut MangaDetail, _| {
+            ctx.submit_command(cmd::EXPORT_VOLUME.with((chapter_range(data), format)))
+        })
+        .fix_width(112.5)
+}
+
 pub fn manga_widget() -> impl Widget<Manga> {
     Flex::column()
         .with_child(
@@ -37,8 +78,34 @@ pub fn manga_widget() -> impl Widget<Manga> {
         })
 }
 
-pub fn mangas_widget() -> impl Widget<Vector<Manga>> {
-    Scroll::new(GridView::new(manga::manga_widget).wrap().with_spacing(10.)).vertical()
+/// Small label with the number of unread chapters, empty when there are none.
+pub fn unread_badge<T: Data>(unread: impl Fn(&T) -> usize + 'static) -> impl Widget<T> {
+    Label::dynamic(move |data: &T, _| match unread(data) {
+        0 => String::new(),
+        unread => format!("{} unread", unread),
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+}
+
+/// A manga card with the unread badge of its library entry.
+fn library_manga_widget() -> impl Widget<(Library, Manga)> {
+    Flex::column()
+        .with_child(manga::manga_widget().lens(lens!((Library, Manga), 1)))
+        .with_child(unread_badge(|(library, manga): &(Library, Manga)| {
+            library
+                .get(&manga.url)
+                .map(|entry| entry.unread())
+                .unwrap_or_default()
+        }))
+}
+
+pub fn mangas_grid() -> impl Widget<(Library, Vector<Manga>)> {
+    GridView::new(library_manga_widget).wrap().with_spacing(10.)
+}
+
+pub fn mangas_widget() -> impl Widget<(Library, Vector<Manga>)> {
+    Scroll::new(mangas_grid()).vertical()
 }
 
 pub fn manga_page_widget() -> impl Widget<Option<MangaDetail>> {
@@ -61,14 +128,50 @@ pub fn manga_page_widget() -> impl Widget<Option<MangaDetail>> {
                                 .fix_size(225., 325.)
                                 .background(Color::BLACK),
                             )
+                            .with_child(
+                                Button::dynamic(|data: &MangaDetail, _| {
+                                    if data.in_library {
+                                        "Remove from library"
+                                    } else {
+                                        "Add to library"
+                                    }
+                                    .to_string()
+                                })
+                                .on_click(|ctx, data: &mut MangaDetail, _| {
+                                    if data.in_library {
+                                        ctx.submit_command(
+                                            cmd::REMOVE_FROM_LIBRARY.with(data.manga.url.clone()),
+                                        )
+                                    } else {
+                                        ctx.submit_command(
+                                            cmd::ADD_TO_LIBRARY.with(data.manga.clone()),
+                                        )
+                                    }
+                                })
+                                .fix_width(225.),
+                            )
+                            .with_child(Either::new(
+                                |data: &MangaDetail, _| data.in_library,
+                                Button::dynamic(|data: &MangaDetail, _| {
+                                    if data.watch {
+                                        "Stop checking for updates"
+                                    } else {
+                                        "Check for updates"
+                                    }
+                                    .to_string()
+                                })
+                                .on_click(|ctx, data: &mut MangaDetail, _| {
+                                    ctx.submit_command(
+                                        cmd::SET_WATCH.with((data.manga.url.clone(), !data.watch)),
+                                    )
+                                })
+                                .fix_width(225.),
+                                SizedBox::empty(),
+                            ))
                             .with_child(
                                 Button::new("Download")
                                     .on_click(|ctx, data: &mut MangaDetail, _| {
-                                        let chapters = data
-                                            .chapters
-                                            .clone()
-                                            .slice((data.start-1) as usize..data.end as usize);
-                                        for chapter in chapters {
+                                        for chapter in chapter_range(data) {
                                             ctx.submit_command(cmd::DOWNLOAD_CHAPTER.with(chapter))
                                         }
                                     })
@@ -92,6 +195,11 @@ pub fn manga_page_widget() -> impl Widget<Option<MangaDetail>> {
                                         .lens(MangaDetail::end)
                                         .align_right(),
                                     ),
+                            )
+                            .with_child(
+                                Flex::row()
+                                    .with_child(export_button(VolumeFormat::Epub))
+                                    .with_child(export_button(VolumeFormat::Pdf)),
                             ),
                     )
                     .with_spacer(30.)
@@ -105,6 +213,11 @@ pub fn manga_page_widget() -> impl Widget<Option<MangaDetail>> {
                                     .with_text_color(theme::TEXT_COLOR)
                                     .lens(MangaDetail::manga.then(Manga::title)),
                             )
+                            .with_child(
+                                manga_details_widget()
+                                    .lens(MangaDetail::manga.then(Manga::details)),
+                            )
+                            .with_spacer(theme::grid(2.))
                             .with_flex_child(
                                 FutureWidget::new(
                                     |data: &MangaDetail, _| {
@@ -113,9 +226,7 @@ pub fn manga_page_widget() -> impl Widget<Option<MangaDetail>> {
                                         async move {
                                             let mut chapters: Vector<Chapter> = Vector::new();
                                             let mut stream = GlobalAPI::global()
-                                                .connectors
-                                                .get(&connector)
-                                                .unwrap()
+                                                .connector(&connector)?
                                                 .get_chapters(manga);
                                             while let Some(res) = stream.next().await {
                                                 chapters.push_front(res?)
@@ -127,10 +238,27 @@ pub fn manga_page_widget() -> impl Widget<Option<MangaDetail>> {
                                     |value: Box<Result<Vector<Chapter>, Error>>,
                                      data: &mut MangaDetail,
                                      _| {
-                                        if let Ok(chapters) = *value {
-                                            data.end = chapters.len() as u16;
-                                            data.chapters = chapters;
+                                        let chapters = match *value {
+                                            Ok(chapters) => chapters,
+                                            Err(err) => {
+                                                return Label::new(err.to_string())
+                                                    .with_line_break_mode(LineBreaking::WordWrap)
+                                                    .with_text_color(theme::RED)
+                                                    .boxed()
+                                            }
+                                        };
+                                        let numbers: Vec<f32> = chapters
+                                            .iter()
+                                            .filter_map(|chapter| chapter.number)
+                                            .collect();
+                                        data.start =
+                                            numbers.iter().copied().fold(f32::NAN, f32::min);
+                                        data.end = numbers.iter().copied().fold(f32::NAN, f32::max);
+                                        if data.start.is_nan() {
+                                            data.start = 0.;
+                                            data.end = 0.;
                                         }
+                                        data.chapters = chapters;
                                         chapters_widget().boxed()
                                     },
                                 ),
@@ -141,6 +269,55 @@ pub fn manga_page_widget() -> impl Widget<Option<MangaDetail>> {
                 1.,
             )
         },
-        || Label::new("Hello"),
+        || Spinner::new().fix_size(50., 50.).center(),
     )
 }
+
+fn join(values: &Vector<Arc<str>>) -> String {
+    values
+        .iter()
+        .map(|value| value.as_ref())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn details_row(
+    title: &'static str,
+    value: impl Fn(&MangaDetails) -> String + 'static,
+) -> impl Widget<MangaDetails> {
+    Label::dynamic(move |details: &MangaDetails, _| format!("{}: {}", title, value(details)))
+        .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+        .with_text_color(theme::TEXT_COLOR)
+}
+
+fn manga_details_widget() -> impl Widget<Option<MangaDetails>> {
+    Maybe::or_empty(|| {
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(details_row("Alternative", |details| {
+                join(&details.alt_titles)
+            }))
+            .with_child(details_row("Authors", |details| join(&details.authors)))
+            .with_child(details_row("Status", |details| details.status.to_string()))
+            .with_child(details_row("Genres", |details| join(&details.genres)))
+            .with_child(details_row("Updated", |details| {
+                details
+                    .last_updated
+                    .as_deref()
+                    .unwrap_or("Unknown")
+                    .to_string()
+            }))
+            .with_spacer(theme::grid(1.))
+            .with_child(
+                Label::dynamic(|details: &MangaDetails, _| {
+                    details
+                        .description
+                        .as_deref()
+                        .unwrap_or_default()
+                        .to_string()
+                })
+                .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+                .with_text_color(theme::TEXT_COLOR),
+            )
+    })
+}
@@ -1,7 +1,12 @@
 mod app;
 mod chapter;
+mod downloads;
+mod library;
 mod manga;
+mod reader;
+mod search;
 pub mod theme;
+mod updates;
 
 use druid::{Data, Env, LocalizedString, MenuDesc};
 
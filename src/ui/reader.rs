@@ -0,0 +1,182 @@
+use druid::{
+    lens,
+    widget::{Button, Either, Flex, Label, List, Scroll, Spinner, ViewSwitcher},
+    Widget, WidgetExt,
+};
+use futures::stream;
+
+use crate::{
+    controller::ReaderController,
+    core::{error::Error, Page},
+    data::{cmd, AppState, Nav, ReaderState, ReadingMode, Zoom},
+    widgets::{remote_image::RemoteImage, Maybe, StreamWidget},
+};
+
+use super::theme;
+
+fn page_widget() -> impl Widget<Page> {
+    RemoteImage::new(
+        Spinner::new().fix_size(40., 40.).center(),
+        |page: &Page, _| Some(page.url.clone()),
+    )
+    .with_referer(|page: &Page, _| Some(page.referer.clone()))
+}
+
+fn paged_widget(zoom: Zoom) -> Box<dyn Widget<ReaderState>> {
+    let page = Maybe::new(page_widget, || Spinner::new().fix_size(40., 40.).center()).lens(
+        lens::Map::new(
+            |reader: &ReaderState| reader.page().cloned(),
+            |_: &mut ReaderState, _| {},
+        ),
+    );
+    match zoom {
+        Zoom::FitWidth => Scroll::new(page).vertical().boxed(),
+        Zoom::FitHeight => Scroll::new(page).horizontal().center().boxed(),
+        Zoom::Original => Scroll::new(page).center().boxed(),
+    }
+}
+
+fn vertical_widget(zoom: Zoom) -> Box<dyn Widget<ReaderState>> {
+    let strip = Flex::column()
+        .with_child(List::new(page_widget).lens(ReaderState::pages))
+        .with_spacer(theme::grid(2.))
+        .with_child(Either::new(
+            |reader: &ReaderState, _| reader.next_chapter().is_some(),
+            Button::new("Next chapter")
+                .on_click(|ctx, _, _| ctx.submit_command(cmd::TURN_CHAPTER.with(1))),
+            Label::new("Last chapter").with_text_color(theme::PLACEHOLDER_COLOR),
+        ))
+        .with_spacer(theme::grid(2.));
+    match zoom {
+        Zoom::FitWidth => Scroll::new(strip).vertical().boxed(),
+        Zoom::FitHeight | Zoom::Original => Scroll::new(strip).boxed(),
+    }
+}
+
+fn toolbar_widget() -> impl Widget<AppState> {
+    Flex::row()
+        .with_child(Button::new("Back").on_click(|ctx, data: &mut AppState, _| {
+            if let Some(reader) = &data.reader {
+                ctx.submit_command(
+                    cmd::NAVIGATE.with(Nav::MangaPage(reader.chapter.manga.url.clone())),
+                )
+            }
+        }))
+        .with_default_spacer()
+        .with_child(
+            Button::new("Previous chapter")
+                .on_click(|ctx, _, _| ctx.submit_command(cmd::TURN_CHAPTER.with(-1))),
+        )
+        .with_child(Button::new("<").on_click(|ctx, data: &mut AppState, _| {
+            let delta = if data.config.reader.right_to_left {
+                1
+            } else {
+                -1
+            };
+            ctx.submit_command(cmd::TURN_PAGE.with(delta))
+        }))
+        .with_child(
+            Label::dynamic(
+                |data: &AppState, _| match (&data.reader, data.config.reader.mode) {
+                    (Some(reader), ReadingMode::Paged) if !reader.pages.is_empty() => {
+                        format!("{} / {}", reader.current + 1, reader.pages.len())
+                    }
+                    (Some(reader), _) => format!("{} pages", reader.pages.len()),
+                    (None, _) => String::new(),
+                },
+            )
+            .with_text_color(theme::TEXT_COLOR)
+            .padding((theme::grid(1.), 0.)),
+        )
+        .with_child(Button::new(">").on_click(|ctx, data: &mut AppState, _| {
+            let delta = if data.config.reader.right_to_left {
+                -1
+            } else {
+                1
+            };
+            ctx.submit_command(cmd::TURN_PAGE.with(delta))
+        }))
+        .with_child(
+            Button::new("Next chapter")
+                .on_click(|ctx, _, _| ctx.submit_command(cmd::TURN_CHAPTER.with(1))),
+        )
+        .with_flex_spacer(1.)
+        .with_child(
+            Button::dynamic(|data: &AppState, _| data.config.reader.mode.to_string()).on_click(
+                |_, data: &mut AppState, _| {
+                    data.config.reader.mode = match data.config.reader.mode {
+                        ReadingMode::Paged => ReadingMode::Vertical,
+                        ReadingMode::Vertical => ReadingMode::Paged,
+                    };
+                    data.config.save();
+                },
+            ),
+        )
+        .with_child(
+            Button::dynamic(|data: &AppState, _| {
+                if data.config.reader.right_to_left {
+                    "Right to left"
+                } else {
+                    "Left to right"
+                }
+                .to_string()
+            })
+            .on_click(|_, data: &mut AppState, _| {
+                data.config.reader.right_to_left = !data.config.reader.right_to_left;
+                data.config.save();
+            }),
+        )
+        .with_child(
+            Button::dynamic(|data: &AppState, _| data.config.reader.zoom.to_string()).on_click(
+                |_, data: &mut AppState, _| {
+                    data.config.reader.zoom = data.config.reader.zoom.next();
+                    data.config.save();
+                },
+            ),
+        )
+        .padding(theme::grid(1.))
+}
+
+/// Rebuilt on every page turn in paged mode so each page starts scrolled to
+/// its top.
+fn pages_widget() -> impl Widget<AppState> {
+    ViewSwitcher::new(
+        |data: &AppState, _| {
+            let reader = &data.config.reader;
+            let current = match (&data.reader, reader.mode) {
+                (Some(state), ReadingMode::Paged) => state.current,
+                _ => 0,
+            };
+            (reader.mode, reader.zoom, current)
+        },
+        |&(mode, zoom, _): &(ReadingMode, Zoom, usize), _, _| {
+            Maybe::or_empty(move || match mode {
+                ReadingMode::Paged => paged_widget(zoom),
+                ReadingMode::Vertical => vertical_widget(zoom),
+            })
+            .lens(AppState::reader)
+            .boxed()
+        },
+    )
+}
+
+pub fn reader_widget() -> impl Widget<AppState> {
+    StreamWidget::new(
+        Flex::column()
+            .with_child(toolbar_widget())
+            .with_flex_child(pages_widget().expand_width(), 1.)
+            .controller(ReaderController),
+        |data: &AppState, _| match &data.reader {
+            Some(reader) => reader.chapter.get_pages(),
+            None => Box::pin(stream::empty()),
+        },
+        |value: Box<Result<Page, Error>>, data: &mut AppState, _| match *value {
+            Ok(page) => {
+                if let Some(reader) = data.reader.as_mut() {
+                    reader.pages.push_back(page)
+                }
+            }
+            Err(err) => data.error = Some(err),
+        },
+    )
+}
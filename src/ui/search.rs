@@ -0,0 +1,71 @@
+use druid::{
+    im::Vector,
+    lens,
+    widget::{CrossAxisAlignment, Flex, Label, List, Scroll},
+    Widget, WidgetExt,
+};
+
+use crate::{
+    core::{error::Error, GlobalAPI, Manga},
+    data::{AppState, Library, Nav, SearchGroup},
+    widgets::StreamWidget,
+};
+
+use super::{manga::mangas_grid, theme};
+
+fn search_group_widget() -> impl Widget<(Library, SearchGroup)> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::dynamic(|(_, data): &(Library, SearchGroup), _| data.connector.to_string())
+                .with_font(theme::UI_FONT_MEDIUM)
+                .with_text_color(theme::TEXT_COLOR)
+                .padding((0., theme::grid(2.))),
+        )
+        .with_child(mangas_grid().lens(lens::Map::new(
+            |(library, group): &(Library, SearchGroup)| (library.clone(), group.mangas.clone()),
+            |(_, group): &mut (Library, SearchGroup), (_, mangas)| group.mangas = mangas,
+        )))
+}
+
+pub fn search_widget() -> impl Widget<AppState> {
+    StreamWidget::new(
+        Scroll::new(
+            List::new(search_group_widget)
+                .with_spacing(theme::grid(2.))
+                .lens(lens::Map::new(
+                    |data: &AppState| (data.library.clone(), data.search_results.clone()),
+                    |data: &mut AppState, (_, results)| data.search_results = results,
+                )),
+        )
+        .vertical(),
+        |data: &AppState, _| {
+            let query = match &data.route {
+                Nav::Search(query) => query.to_string(),
+                _ => String::new(),
+            };
+            GlobalAPI::global().search(&query, 1)
+        },
+        |value: Box<Result<Manga, Error>>, data: &mut AppState, _| match *value {
+            Ok(manga) => {
+                data.manga_cache
+                    .lock()
+                    .unwrap()
+                    .insert(manga.url.clone(), manga.clone());
+                if let Some(group) = data
+                    .search_results
+                    .iter_mut()
+                    .find(|group| group.connector == manga.connector)
+                {
+                    group.mangas.push_back(manga);
+                } else {
+                    data.search_results.push_back(SearchGroup {
+                        connector: manga.connector.clone(),
+                        mangas: Vector::unit(manga),
+                    });
+                }
+            }
+            Err(err) => data.error = Some(err),
+        },
+    )
+}
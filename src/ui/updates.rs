@@ -0,0 +1,92 @@
+use chrono::Local;
+use druid::{
+    widget::{Button, CrossAxisAlignment, Either, Flex, Label, List, Scroll},
+    LensExt, UnitPoint, Widget, WidgetExt,
+};
+
+use crate::{
+    core::{Chapter, Manga},
+    data::{cmd, AppState, ChapterUpdate, Library, Nav},
+    widgets::MyWidgetExt,
+};
+
+use super::{chapter::chapter_widget, theme};
+
+fn update_widget() -> impl Widget<ChapterUpdate> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_flex_child(
+                    Label::raw()
+                        .with_font(theme::UI_FONT_MEDIUM)
+                        .with_text_color(theme::TEXT_COLOR)
+                        .lens(
+                            ChapterUpdate::chapter
+                                .then(Chapter::manga)
+                                .then(Manga::title),
+                        )
+                        .on_click(|ctx, data: &mut ChapterUpdate, _| {
+                            ctx.submit_command(
+                                cmd::NAVIGATE.with(Nav::MangaPage(data.chapter.manga.url.clone())),
+                            )
+                        })
+                        .align_left(),
+                    1.,
+                )
+                .with_child(
+                    Label::dynamic(|data: &ChapterUpdate, _| {
+                        data.found
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .with_text_size(theme::TEXT_SIZE_SMALL)
+                    .with_text_color(theme::PLACEHOLDER_COLOR),
+                ),
+        )
+        .with_child(chapter_widget().lens(ChapterUpdate::chapter))
+}
+
+pub fn updates_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Button::dynamic(|checking: &bool, _| {
+                if *checking {
+                    "Checking for updates..."
+                } else {
+                    "Check now"
+                }
+                .to_string()
+            })
+            .on_click(|ctx, checking: &mut bool, _| {
+                if !*checking {
+                    ctx.submit_command(cmd::CHECK_UPDATES)
+                }
+            })
+            .padding(theme::grid(1.))
+            .lens(AppState::checking_updates),
+        )
+        .with_flex_child(
+            Either::new(
+                |library: &Library, _| library.updates.is_empty(),
+                Label::new(
+                    "No new chapters yet, watched manga in your library are checked regularly.",
+                )
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .center(),
+                Scroll::new(
+                    List::new(update_widget)
+                        .with_spacing(theme::grid(2.))
+                        .padding_right(theme::grid(1.))
+                        .lens(Library::updates),
+                )
+                .vertical()
+                .align_vertical(UnitPoint::TOP)
+                .expand_width(),
+            )
+            .lens(AppState::library),
+            1.,
+        )
+}
@@ -0,0 +1,86 @@
+use druid::{
+    widget::{prelude::*, Button, Flex},
+    Point, Selector, WidgetExt, WidgetId, WidgetPod,
+};
+
+/// Shows the confirmation of a `ConfirmButton`, or hides it with `false`.
+const ASK: Selector<bool> = Selector::new("confirm-button.ask");
+
+/// A button running its action only once confirmed, for actions that cannot
+/// be undone.
+pub struct ConfirmButton<T> {
+    id: WidgetId,
+    asking: bool,
+    button: WidgetPod<T, Box<dyn Widget<T>>>,
+    confirm: WidgetPod<T, Box<dyn Widget<T>>>,
+}
+
+impl<T: Data> ConfirmButton<T> {
+    pub fn new(text: &str, action: impl Fn(&mut EventCtx, &mut T, &Env) + 'static) -> Self {
+        let id = WidgetId::next();
+        let button =
+            Button::new(text).on_click(move |ctx, _, _| ctx.submit_command(ASK.with(true).to(id)));
+        let confirm = Flex::row()
+            .with_child(Button::new("Confirm").on_click(move |ctx, data, env| {
+                action(ctx, data, env);
+                ctx.submit_command(ASK.with(false).to(id))
+            }))
+            .with_child(
+                Button::new("Cancel")
+                    .on_click(move |ctx, _, _| ctx.submit_command(ASK.with(false).to(id))),
+            );
+        Self {
+            id,
+            asking: false,
+            button: WidgetPod::new(button.boxed()),
+            confirm: WidgetPod::new(confirm.boxed()),
+        }
+    }
+
+    fn shown(&mut self) -> &mut WidgetPod<T, Box<dyn Widget<T>>> {
+        if self.asking {
+            &mut self.confirm
+        } else {
+            &mut self.button
+        }
+    }
+}
+
+impl<T: Data> Widget<T> for ConfirmButton<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if let Event::Command(cmd) = event {
+            if let Some(asking) = cmd.get(ASK) {
+                self.asking = *asking;
+                ctx.request_layout();
+                ctx.set_handled();
+                return;
+            }
+        }
+        self.shown().event(ctx, event, data, env);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.button.lifecycle(ctx, event, data, env);
+        self.confirm.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        self.button.update(ctx, data, env);
+        self.confirm.update(ctx, data, env);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let shown = self.shown();
+        let size = shown.layout(ctx, bc, data, env);
+        shown.set_origin(ctx, data, env, Point::ORIGIN);
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.shown().paint(ctx, data, env);
+    }
+
+    fn id(&self) -> Option<WidgetId> {
+        Some(self.id)
+    }
+}
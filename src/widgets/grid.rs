@@ -255,6 +255,41 @@ impl<T: Data> GridIter<T> for Vector<T> {
     }
 }
 
+// S == shared data type
+impl<S: Data, T: Data> GridIter<(S, T)> for (S, Vector<T>) {
+    fn for_each(&self, mut cb: impl FnMut(&(S, T), usize)) {
+        for (i, item) in self.1.iter().enumerate() {
+            let d = (self.0.to_owned(), item.to_owned());
+            cb(&d, i);
+        }
+    }
+
+    fn for_each_mut(&mut self, mut cb: impl FnMut(&mut (S, T), usize)) {
+        for (i, item) in self.1.iter_mut().enumerate() {
+            let mut d = (self.0.clone(), item.clone());
+            cb(&mut d, i);
+
+            if !self.0.same(&d.0) {
+                self.0 = d.0;
+            }
+            if !item.same(&d.1) {
+                *item = d.1;
+            }
+        }
+    }
+
+    fn data_len(&self) -> usize {
+        self.1.len()
+    }
+
+    fn child_data(&self) -> Option<(S, T)> {
+        self.1
+            .iter()
+            .next()
+            .map(|item| (self.0.clone(), item.clone()))
+    }
+}
+
 impl<C: Data, T: GridIter<C>> Widget<T> for GridView<C> {
     fn event(
         &mut self,
@@ -0,0 +1,367 @@
+//! Fetches the images of [`RemoteImage`] widgets, the ones on screen first.
+//!
+//! Widgets register their image when added and, until it is fetched, report
+//! themselves visible each time they are painted, which only happens inside
+//! the viewport. Visible
+//! images are fetched most recently painted first, then the next
+//! [`ImageConfig::prefetch`] registrations after the last visible one, which
+//! in a grid or list are the cards right below the viewport. At most
+//! [`ImageConfig::concurrency`] images are fetched at once and dropping a
+//! widget cancels its fetch. A failed fetch is tried again a little later, up
+//! to [`MAX_ATTEMPTS`] times before the widgets are told it failed.
+//!
+//! [`RemoteImage`]: super::remote_image::RemoteImage
+//! [`ImageConfig::prefetch`]: crate::data::ImageConfig::prefetch
+//! [`ImageConfig::concurrency`]: crate::data::ImageConfig::concurrency
+
+use std::{
+    collections::HashMap,
+    io::{copy, Cursor},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use druid::{
+    image::{self, ImageFormat},
+    ExtEventSink, ImageBuf, Target, WidgetId,
+};
+use lru_cache::LruCache;
+use once_cell::sync::OnceCell;
+use reqwest::{
+    header::{self, CONTENT_TYPE},
+    Url,
+};
+use tokio::{task::JoinHandle, time};
+
+use crate::{
+    core::{local::read_archive_page, GlobalAPI},
+    data::ImageConfig,
+};
+
+use super::remote_image::{ImagePayload, ImageRequest, IMAGE_FAILED, PROVIDE_DATA};
+
+static IMAGE_LOADER: OnceCell<ImageLoader> = OnceCell::new();
+
+const MEMORY_CACHE_SIZE: usize = 256;
+/// Fetches of an image before it is given up on.
+const MAX_ATTEMPTS: u32 = 3;
+/// Wait before fetching a failed image again, times its failures so far.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    Pending,
+    Loading,
+    /// Failed, waiting to be fetched again.
+    Retrying,
+    Done,
+    Failed,
+}
+
+struct Entry {
+    request: ImageRequest,
+    /// Registration order, the order of widgets in a grid or list.
+    seq: u64,
+    /// Paint count at the last paint, `None` until painted once.
+    painted: Option<u64>,
+    status: Status,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<WidgetId, Entry>,
+    in_flight: HashMap<Arc<str>, JoinHandle<()>>,
+    /// Failed fetches by location, until one succeeds.
+    failures: HashMap<Arc<str>, u32>,
+    next_seq: u64,
+    paints: u64,
+    last_painted_seq: u64,
+}
+
+impl State {
+    /// The next image to fetch: the most recently painted one, else the first
+    /// one in the prefetch window.
+    fn next(&self, prefetch: u64) -> Option<Arc<str>> {
+        let pending = || {
+            self.entries.values().filter(|entry| {
+                entry.status == Status::Pending
+                    && !self.in_flight.contains_key(&entry.request.location)
+            })
+        };
+        pending()
+            .filter(|entry| entry.painted.is_some())
+            .max_by_key(|entry| (entry.painted, std::cmp::Reverse(entry.seq)))
+            .or_else(|| {
+                pending()
+                    .filter(|entry| {
+                        entry.seq > self.last_painted_seq
+                            && entry.seq <= self.last_painted_seq + prefetch
+                    })
+                    .min_by_key(|entry| entry.seq)
+            })
+            .map(|entry| entry.request.location.clone())
+    }
+
+    fn set_status(&mut self, location: &str, from: Status, to: Status) -> Vec<WidgetId> {
+        self.entries
+            .iter_mut()
+            .filter(|(_, entry)| {
+                entry.status == from && entry.request.location.as_ref() == location
+            })
+            .map(|(id, entry)| {
+                entry.status = to;
+                *id
+            })
+            .collect()
+    }
+}
+
+pub struct ImageLoader {
+    event_sink: ExtEventSink,
+    config: ImageConfig,
+    state: Mutex<State>,
+    memory: Mutex<LruCache<Arc<str>, ImageBuf>>,
+}
+
+impl ImageLoader {
+    pub fn install(event_sink: ExtEventSink, config: ImageConfig) {
+        IMAGE_LOADER
+            .set(ImageLoader {
+                event_sink,
+                config,
+                state: Mutex::new(State::default()),
+                memory: Mutex::new(LruCache::new(MEMORY_CACHE_SIZE)),
+            })
+            .ok()
+            .expect("ImageLoader already installed");
+    }
+
+    pub fn global() -> &'static ImageLoader {
+        IMAGE_LOADER
+            .get()
+            .expect("ImageLoader Not Initialized! Did you forget to call ImageLoader::install?")
+    }
+
+    /// Registers the image of `widget`, replacing its previous one. Returns
+    /// the image right away when it is in memory. An empty location, like
+    /// the cover of a manga without one, is not fetched.
+    pub fn request(&'static self, widget: WidgetId, request: ImageRequest) -> Option<ImageBuf> {
+        self.release(widget);
+        if request.location.is_empty() {
+            return None;
+        }
+        let cached = self
+            .memory
+            .lock()
+            .unwrap()
+            .get_mut(&request.location)
+            .cloned();
+
+        let mut state = self.state.lock().unwrap();
+        let status = if cached.is_some() {
+            Status::Done
+        } else if state.in_flight.contains_key(&request.location) {
+            Status::Loading
+        } else {
+            Status::Pending
+        };
+        state.next_seq += 1;
+        let seq = state.next_seq;
+        state.entries.insert(
+            widget,
+            Entry {
+                request,
+                seq,
+                painted: None,
+                status,
+            },
+        );
+        self.pump(&mut state);
+        cached
+    }
+
+    /// Marks `widget` as on screen, while its image waits to be fetched.
+    pub fn visible(&'static self, widget: WidgetId) {
+        let mut state = self.state.lock().unwrap();
+        let paints = state.paints + 1;
+        let seq = match state.entries.get_mut(&widget) {
+            Some(entry) if matches!(entry.status, Status::Pending | Status::Retrying) => {
+                entry.painted = Some(paints);
+                entry.seq
+            }
+            _ => return,
+        };
+        state.paints = paints;
+        state.last_painted_seq = seq;
+        self.pump(&mut state);
+    }
+
+    /// Forgets the image of `widget`, cancelling its fetch unless another
+    /// widget waits on the same image.
+    pub fn release(&'static self, widget: WidgetId) {
+        let mut state = self.state.lock().unwrap();
+        let entry = match state.entries.remove(&widget) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let location = &entry.request.location;
+        let waited = state
+            .entries
+            .values()
+            .any(|other| other.status == Status::Loading && &other.request.location == location);
+        if entry.status == Status::Loading && !waited {
+            if let Some(task) = state.in_flight.remove(location) {
+                task.abort();
+            }
+            self.pump(&mut state);
+        }
+    }
+
+    fn pump(&'static self, state: &mut State) {
+        while state.in_flight.len() < self.config.concurrency.max(1) {
+            let location = match state.next(self.config.prefetch) {
+                Some(location) => location,
+                None => break,
+            };
+            state.set_status(&location, Status::Pending, Status::Loading);
+            let referer = state
+                .entries
+                .values()
+                .find(|entry| entry.request.location == location)
+                .and_then(|entry| entry.request.referer.clone());
+            let task = tokio::spawn({
+                let location = location.clone();
+                async move {
+                    let result = load_image(&location, referer.as_deref()).await;
+                    self.finish(location, result);
+                }
+            });
+            state.in_flight.insert(location, task);
+        }
+    }
+
+    fn finish(&'static self, location: Arc<str>, result: Result<ImageBuf, String>) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight.remove(&location);
+        match result {
+            Ok(image_buf) => {
+                state.failures.remove(&location);
+                let widgets = state.set_status(&location, Status::Loading, Status::Done);
+                self.memory
+                    .lock()
+                    .unwrap()
+                    .insert(location.clone(), image_buf.clone());
+                for widget in widgets {
+                    let payload = ImagePayload {
+                        location: location.clone(),
+                        image_buf: image_buf.clone(),
+                    };
+                    self.event_sink
+                        .submit_command(PROVIDE_DATA, payload, Target::Widget(widget))
+                        .expect("Command failed to submit");
+                }
+            }
+            Err(err) => {
+                let failures = state.failures.entry(location.clone()).or_default();
+                *failures += 1;
+                let failures = *failures;
+                if failures < MAX_ATTEMPTS {
+                    log::warn!("failed to load image {}: {}, retrying", location, err);
+                    state.set_status(&location, Status::Loading, Status::Retrying);
+                    tokio::spawn(async move {
+                        time::sleep(RETRY_DELAY * failures).await;
+                        self.retry(&location);
+                    });
+                } else {
+                    log::error!("failed to load image {}: {}", location, err);
+                    state.failures.remove(&location);
+                    for widget in state.set_status(&location, Status::Loading, Status::Failed) {
+                        self.event_sink
+                            .submit_command(IMAGE_FAILED, location.clone(), Target::Widget(widget))
+                            .expect("Command failed to submit");
+                    }
+                }
+            }
+        }
+        self.pump(&mut state);
+    }
+
+    fn retry(&'static self, location: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.set_status(location, Status::Retrying, Status::Pending);
+        self.pump(&mut state);
+    }
+}
+
+/// Names the cached copy of an image after its whole url, chapter pages of
+/// different manga often share a file name.
+fn image_key(location: &str) -> String {
+    let location = location
+        .split_once("://")
+        .map_or(location, |(_, location)| location);
+    sanitize_filename::sanitize_with_options(
+        location,
+        sanitize_filename::Options {
+            replacement: "_",
+            ..Default::default()
+        },
+    )
+}
+
+async fn load_image(location: &str, referer: Option<&str>) -> Result<ImageBuf, String> {
+    // Downloaded pages are read in place.
+    if let Some(url) = Url::parse(location)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+    {
+        let path = url
+            .to_file_path()
+            .map_err(|_| format!("Invalid path {}", location))?;
+        return match url.fragment() {
+            // A page of a chapter kept as a `.cbz`.
+            Some(name) => read_archive_page(&path, name)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| ImageBuf::from_data(&bytes).map_err(|err| err.to_string())),
+            None => ImageBuf::from_file(path).map_err(|err| err.to_string()),
+        };
+    }
+
+    let cache = &GlobalAPI::global().cache;
+    let key = image_key(location);
+    if let Some(image_buf) = cache.get_image("images", &key) {
+        return Ok(image_buf);
+    }
+    let image_buf = ImageBuf::from_dynamic_image(get_image(location, referer).await?);
+    cache.set_image("images", &key, &image_buf);
+    Ok(image_buf)
+}
+
+async fn get_image(uri: &str, referer: Option<&str>) -> Result<image::DynamicImage, String> {
+    let http = &GlobalAPI::global().http;
+    let response = http
+        .send(
+            http.get(uri)
+                .header(header::REFERER, referer.unwrap_or(uri)),
+        )
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?;
+    let format = match response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some("image/jpeg") => Some(ImageFormat::Jpeg),
+        Some("image/png") => Some(ImageFormat::Png),
+        _ => None,
+    };
+    let mut content = Cursor::new(response.bytes().await.map_err(|err| err.to_string())?);
+    let mut body = vec![];
+    copy(&mut content, &mut body).map_err(|err| err.to_string())?;
+    if let Some(format) = format {
+        image::load_from_memory_with_format(&body, format)
+    } else {
+        image::load_from_memory(&body)
+    }
+    .map_err(|err| err.to_string())
+}
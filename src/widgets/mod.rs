@@ -1,9 +1,11 @@
 mod async_delegate;
+mod confirm_button;
 mod dynamic_sized_box;
 pub mod future_widget;
 mod grid;
 mod hover;
 pub mod icons;
+pub mod image_loader;
 mod link;
 mod maybe;
 mod progress_bar;
@@ -16,17 +18,19 @@ mod utils;
 use druid::{widget::Padding, Color, Data, Insets, KeyOrValue, Widget};
 
 pub use async_delegate::AsyncDelegate;
+pub use confirm_button::ConfirmButton;
 pub use dynamic_sized_box::DynamicSizedBox;
 pub use future_widget::FutureWidget;
 pub use grid::GridView;
 pub use hover::Hover;
+pub use image_loader::ImageLoader;
 pub use link::Link;
 pub use maybe::Maybe;
 pub use progress_bar::ProgressBar;
 pub use stream_widget::StreamWidget;
 pub use theme::ThemeScope;
 pub use titlebar::TitleBar;
-pub use utils::{Border, Clip, Logger};
+pub use utils::{Border, Clip, Logger, Overlay};
 
 pub trait MyWidgetExt<T: Data>: Widget<T> + Sized + 'static {
     fn log(self, label: &'static str) -> Logger<Self> {
@@ -1,12 +1,24 @@
 use std::sync::Arc;
 
 use druid::{
-    widget::{prelude::*, Image},
-    Data, ImageBuf, Point, Selector, WidgetPod,
+    widget::{prelude::*, Image, Label},
+    Data, ImageBuf, Point, Selector, WidgetExt, WidgetId, WidgetPod,
 };
 
-pub const REQUEST_DATA: Selector<Arc<str>> = Selector::new("remote-image.request-data");
+use crate::ui::theme;
+
+use super::image_loader::ImageLoader;
+
 pub const PROVIDE_DATA: Selector<ImagePayload> = Selector::new("remote-image.provide-data");
+/// The image at this location could not be loaded.
+pub const IMAGE_FAILED: Selector<Arc<str>> = Selector::new("remote-image.image-failed");
+
+#[derive(Clone)]
+pub struct ImageRequest {
+    pub location: Arc<str>,
+    /// Sent as `Referer`, some hosts refuse hotlinked images.
+    pub referer: Option<Arc<str>>,
+}
 
 #[derive(Clone)]
 pub struct ImagePayload {
@@ -14,11 +26,18 @@ pub struct ImagePayload {
     pub image_buf: ImageBuf,
 }
 
+/// Picks an url out of the data.
+type UrlFn<T> = Box<dyn Fn(&T, &Env) -> Option<Arc<str>>>;
+
 pub struct RemoteImage<T> {
     placeholder: WidgetPod<T, Box<dyn Widget<T>>>,
+    /// Shown instead of the placeholder once the image failed to load.
+    failed: Option<WidgetPod<T, Box<dyn Widget<T>>>>,
     image: Option<WidgetPod<T, Image>>,
-    locator: Box<dyn Fn(&T, &Env) -> Option<Arc<str>>>,
+    locator: UrlFn<T>,
+    referer: Option<UrlFn<T>>,
     location: Option<Arc<str>>,
+    id: Option<WidgetId>,
 }
 
 impl<T: Data> RemoteImage<T> {
@@ -28,11 +47,51 @@ impl<T: Data> RemoteImage<T> {
     ) -> Self {
         Self {
             placeholder: WidgetPod::new(placeholder).boxed(),
+            failed: None,
             locator: Box::new(locator),
+            referer: None,
             location: None,
+            id: None,
             image: None,
         }
     }
+
+    pub fn with_referer(
+        mut self,
+        referer: impl Fn(&T, &Env) -> Option<Arc<str>> + 'static,
+    ) -> Self {
+        self.referer = Some(Box::new(referer));
+        self
+    }
+
+    /// Asks the image loader for the image at the current location, taking
+    /// it right away when it is in memory.
+    fn request(&mut self, id: WidgetId, data: &T, env: &Env) {
+        let location = (self.locator)(data, env);
+        self.image = None;
+        self.failed = None;
+        self.location = location.clone();
+        match location {
+            Some(location) => {
+                let request = ImageRequest {
+                    location,
+                    referer: self.referer.as_ref().and_then(|referer| referer(data, env)),
+                };
+                if let Some(image_buf) = ImageLoader::global().request(id, request) {
+                    self.image = Some(WidgetPod::new(Image::new(image_buf)));
+                }
+            }
+            None => ImageLoader::global().release(id),
+        }
+    }
+}
+
+impl<T> Drop for RemoteImage<T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            ImageLoader::global().release(id);
+        }
+    }
 }
 
 impl<T: Data> Widget<T> for RemoteImage<T> {
@@ -46,9 +105,25 @@ impl<T: Data> Widget<T> for RemoteImage<T> {
                 }
                 return;
             }
+            if let Some(location) = cmd.get(IMAGE_FAILED) {
+                if Some(location) == self.location.as_ref() {
+                    self.failed = Some(
+                        WidgetPod::new(
+                            Label::new("Image unavailable")
+                                .with_text_color(theme::GREY_400)
+                                .center(),
+                        )
+                        .boxed(),
+                    );
+                    ctx.children_changed();
+                }
+                return;
+            }
         }
         if let Some(image) = self.image.as_mut() {
             image.event(ctx, event, data, env);
+        } else if let Some(failed) = self.failed.as_mut() {
+            failed.event(ctx, event, data, env);
         } else {
             self.placeholder.event(ctx, event, data, env);
         }
@@ -56,32 +131,27 @@ impl<T: Data> Widget<T> for RemoteImage<T> {
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
         if let LifeCycle::WidgetAdded = event {
-            let location = (self.locator)(data, env);
-            self.image = None;
-            self.location = location.clone();
-            if let Some(location) = location {
-                ctx.submit_command(REQUEST_DATA.with(location).to(ctx.widget_id()));
-            }
+            self.id = Some(ctx.widget_id());
+            self.request(ctx.widget_id(), data, env);
         }
         if let Some(image) = self.image.as_mut() {
             image.lifecycle(ctx, event, data, env);
+        } else if let Some(failed) = self.failed.as_mut() {
+            failed.lifecycle(ctx, event, data, env);
         } else {
             self.placeholder.lifecycle(ctx, event, data, env);
         }
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
-        let location = (self.locator)(data, env);
-        if location != self.location {
-            self.image = None;
-            self.location = location.clone();
-            if let Some(location) = location {
-                ctx.submit_command(REQUEST_DATA.with(location).to(ctx.widget_id()));
-            }
+        if (self.locator)(data, env) != self.location {
+            self.request(ctx.widget_id(), data, env);
             ctx.children_changed();
         }
         if let Some(image) = self.image.as_mut() {
             image.update(ctx, data, env);
+        } else if let Some(failed) = self.failed.as_mut() {
+            failed.update(ctx, data, env);
         } else {
             self.placeholder.update(ctx, data, env);
         }
@@ -92,6 +162,10 @@ impl<T: Data> Widget<T> for RemoteImage<T> {
             let size = image.layout(ctx, bc, data, env);
             image.set_origin(ctx, data, env, Point::ORIGIN);
             size
+        } else if let Some(failed) = self.failed.as_mut() {
+            let size = failed.layout(ctx, bc, data, env);
+            failed.set_origin(ctx, data, env, Point::ORIGIN);
+            size
         } else {
             let size = self.placeholder.layout(ctx, bc, data, env);
             self.placeholder.set_origin(ctx, data, env, Point::ORIGIN);
@@ -100,8 +174,14 @@ impl<T: Data> Widget<T> for RemoteImage<T> {
     }
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        // Only called inside the viewport.
+        if self.image.is_none() && self.failed.is_none() {
+            ImageLoader::global().visible(ctx.widget_id());
+        }
         if let Some(image) = self.image.as_mut() {
             image.paint(ctx, data, env)
+        } else if let Some(failed) = self.failed.as_mut() {
+            failed.paint(ctx, data, env)
         } else {
             self.placeholder.paint(ctx, data, env)
         }
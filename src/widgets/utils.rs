@@ -3,7 +3,7 @@
 use druid::{
     kurbo::{Line, Shape},
     widget::{prelude::*, BackgroundBrush, Painter},
-    Color, Data, KeyOrValue,
+    Color, Data, KeyOrValue, Point, WidgetPod,
 };
 
 pub struct Clip<S, W> {
@@ -43,6 +43,57 @@ impl<T: Data, S: Shape, W: Widget<T>> Widget<T> for Clip<S, W> {
     }
 }
 
+/// Paints `overlay` centered over `base`, which gives the size.
+pub struct Overlay<T> {
+    base: WidgetPod<T, Box<dyn Widget<T>>>,
+    overlay: WidgetPod<T, Box<dyn Widget<T>>>,
+}
+
+impl<T: Data> Overlay<T> {
+    pub fn new(base: impl Widget<T> + 'static, overlay: impl Widget<T> + 'static) -> Self {
+        Self {
+            base: WidgetPod::new(base).boxed(),
+            overlay: WidgetPod::new(overlay).boxed(),
+        }
+    }
+}
+
+impl<T: Data> Widget<T> for Overlay<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        self.overlay.event(ctx, event, data, env);
+        self.base.event(ctx, event, data, env);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.base.lifecycle(ctx, event, data, env);
+        self.overlay.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &T, data: &T, env: &Env) {
+        self.base.update(ctx, data, env);
+        self.overlay.update(ctx, data, env);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let size = self.base.layout(ctx, bc, data, env);
+        self.base.set_origin(ctx, data, env, Point::ORIGIN);
+        let overlay = self
+            .overlay
+            .layout(ctx, &BoxConstraints::new(Size::ZERO, size), data, env);
+        let origin = Point::new(
+            (size.width - overlay.width) / 2.,
+            (size.height - overlay.height) / 2.,
+        );
+        self.overlay.set_origin(ctx, data, env, origin);
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.base.paint(ctx, data, env);
+        self.overlay.paint(ctx, data, env);
+    }
+}
+
 pub enum Border {
     Top,
     Bottom,